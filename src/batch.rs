@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+use crate::cache_control::CacheClass;
+use crate::config::parse_repo_name;
+use crate::envelope::Freshness;
+use crate::error::ApiError;
+use crate::{resolve_release, ApiResponse, CacheState};
+
+// one requested release, `org/repo` for latest or `org/repo@tag` for a pinned tag
+struct BatchTarget {
+    org: String,
+    repo: String,
+    tag: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct BatchRequest {
+    pub repos: Vec<String>,
+}
+
+// each entry is either the release, as served by /:org/:repo, or why it couldn't be resolved
+#[derive(Serialize)]
+#[serde(untagged)]
+pub enum BatchEntry {
+    Release(Box<ApiResponse>),
+    Failed {
+        org: String,
+        repo: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        tag: Option<String>,
+        error: BatchError,
+    },
+}
+
+#[derive(Serialize)]
+pub struct BatchError {
+    code: &'static str,
+    message: String,
+}
+
+// ?repos=org/repo,org/repo@tag
+pub async fn get_batch(
+    Query(params): Query<HashMap<String,String>>,
+    State(state): State<CacheState>
+) -> Result<Response, ApiError> {
+    let repos: Vec<String> = params.get("repos").map(String::as_str).unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(str::to_string)
+        .collect();
+    batch(state, &repos).await
+}
+
+// same as GET /batch for a posted list
+pub async fn post_batch(
+    State(state): State<CacheState>,
+    Json(body): Json<BatchRequest>
+) -> Result<Response, ApiError> {
+    batch(state, &body.repos).await
+}
+
+// repo names never contain an @, so everything after the first one is the tag
+fn parse_target(raw: &str) -> Option<BatchTarget> {
+    let (name, tag) = match raw.split_once('@') {
+        Some((name, tag)) => (name, Some(tag).filter(|t| !t.is_empty() && *t != "latest")),
+        None => (raw, None)
+    };
+    let name = parse_repo_name(name)?;
+    Some(BatchTarget {
+        org: name.org,
+        repo: name.repo,
+        tag: tag.map(str::to_string),
+    })
+}
+
+// resolve every entry through the cache, BATCH_CONCURRENCY entries at a time. entries keep the
+// requested order and one failing doesn't fail the others
+async fn batch(state: CacheState, repos: &[String]) -> Result<Response, ApiError> {
+    if repos.is_empty() {
+        return Err(ApiError::new(StatusCode::BAD_REQUEST, "invalid_repo", "expected at least one org/repo"));
+    }
+    if repos.len() > state.config.batch_max_repos {
+        return Err(ApiError::new(StatusCode::BAD_REQUEST, "too_many_repos",
+            format!("at most {} repos per batch", state.config.batch_max_repos)));
+    }
+    let targets = repos.iter()
+        .map(|name| parse_target(name).ok_or_else(|| {
+            ApiError::new(StatusCode::BAD_REQUEST, "invalid_repo", format!("expected org/repo or org/repo@tag, got {:?}", name))
+        }))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let permits = Arc::new(Semaphore::new(state.config.batch_concurrency));
+    let mut tasks = JoinSet::new();
+    for (index, target) in targets.into_iter().enumerate() {
+        let (state, permits) = (state.clone(), permits.clone());
+        tasks.spawn(async move {
+            let _permit = permits.acquire().await.expect("batch semaphore closed");
+            let result = resolve_release(&state, &target.org, &target.repo, target.tag.as_deref(), None).await;
+            (index, target, result)
+        });
+    }
+
+    let mut entries: Vec<Option<BatchEntry>> = (0..repos.len()).map(|_| None).collect();
+    let mut freshness = Freshness { cached: true, age: Default::default() };
+    let mut class = CacheClass::Pinned;
+    let mut failed = false;
+    while let Some(joined) = tasks.join_next().await {
+        let (index, target, result) = match joined {
+            Ok(joined) => joined,
+            // resolving doesn't panic in practice, and there's no target left to report it against
+            Err(e) => {
+                tracing::error!("batch task failed: {}", e);
+                continue;
+            }
+        };
+        entries[index] = Some(match result {
+            Ok(mut res) => {
+                freshness = freshness.oldest(Freshness::of(&res));
+                if CacheClass::for_resolution(res.resolved_by) == CacheClass::Moving {
+                    class = CacheClass::Moving;
+                }
+                res.finalize(&state.config);
+                BatchEntry::Release(Box::new(res))
+            },
+            Err(e) => {
+                failed = true;
+                BatchEntry::Failed {
+                    org: target.org,
+                    repo: target.repo,
+                    tag: target.tag,
+                    error: BatchError { code: e.code, message: e.message },
+                }
+            }
+        });
+    }
+
+    let entries: Vec<BatchEntry> = entries.into_iter().flatten().collect();
+    let mut response = Json(entries).into_response();
+    // a batch with failures is sent as no-store so the errors aren't cached downstream
+    if !failed {
+        response.extensions_mut().insert(class);
+    }
+    response.extensions_mut().insert(freshness);
+    Ok(response)
+}
@@ -0,0 +1,58 @@
+use axum::extract::{Request, State};
+use axum::http::{HeaderValue, StatusCode};
+use axum::http::header::CACHE_CONTROL;
+use axum::middleware::Next;
+use axum::response::Response;
+
+use crate::{CacheState, Lookup};
+
+// how long clients/CDNs may keep a successful response, set by the handler as a response extension
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CacheClass {
+    // a release looked up by tag, the content only changes if the notes are edited
+    Pinned,
+    // anything that moves when a new release ships (latest, prerelease, ...)
+    Moving,
+}
+
+impl CacheClass {
+    pub fn for_resolution(resolved_by: Lookup) -> Self {
+        match resolved_by {
+            Lookup::Tag => CacheClass::Pinned,
+            _ => CacheClass::Moving
+        }
+    }
+}
+
+// one place deciding Cache-Control for every response:
+//   2xx/304 pinned -> public, max-age=CACHE_MAX_AGE_PINNED_SECS
+//   2xx/304 moving -> public, max-age=CACHE_MAX_AGE_LATEST_SECS
+//   404            -> public, max-age=CACHE_MAX_AGE_NOT_FOUND_SECS
+//   429 and 5xx    -> no-store, as are other errors
+// 2xx responses without a CacheClass (e.g. force refresh) get no-store too,
+// and a Cache-Control set by the handler itself is left alone
+pub async fn apply(State(state): State<CacheState>, request: Request, next: Next) -> Response {
+    let mut response = next.run(request).await;
+    if response.headers().contains_key(CACHE_CONTROL) {
+        return response;
+    }
+
+    let config = &state.config;
+    let max_age = match response.status() {
+        status if status.is_success() || status == StatusCode::NOT_MODIFIED => match response.extensions().get::<CacheClass>() {
+            Some(CacheClass::Pinned) => Some(config.cache_max_age_pinned),
+            Some(CacheClass::Moving) => Some(config.cache_max_age_latest),
+            None => None
+        },
+        StatusCode::NOT_FOUND => Some(config.cache_max_age_not_found),
+        _ => None
+    };
+    let value = match max_age {
+        Some(secs) if secs > 0 => format!("public, max-age={}", secs),
+        _ => "no-store".to_string()
+    };
+    if let Ok(value) = HeaderValue::from_str(&value) {
+        response.headers_mut().insert(CACHE_CONTROL, value);
+    }
+    response
+}
@@ -0,0 +1,302 @@
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::env;
+use std::hash::{BuildHasher, Hasher};
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::Lookup;
+
+// settings read from the environment at startup, so the same binary works across deployments
+// (the github PAT is the exception, it's baked in at compile time via dotenv!)
+#[derive(Debug, Clone)]
+pub struct Config {
+    // REPO_META_TTL_SECS: how long repo metadata (stars, homepage, default branch) is reused,
+    // stars change slowly so this defaults to an hour
+    pub repo_meta_ttl: Duration,
+    // POLL_REPOS: repos refreshed in the background, comma separated `org/repo` or `org/repo=<secs>`,
+    // entries without an interval use POLL_INTERVAL_SECS (default 300)
+    pub poll_repos: Vec<PollTarget>,
+    // FEED_REPOS: comma separated `org/repo` list merged by GET /feed
+    pub feed_repos: Vec<RepoName>,
+    // FEED_CONCURRENCY: how many feed repos are resolved at once (default 4)
+    pub feed_concurrency: usize,
+    // FEED_MAX_REPOS: most repos a POST /feed may ask for, each can cost a github call (default 50)
+    pub feed_max_repos: usize,
+    // FEED_MAX_LIMIT: upper bound for the feed's ?limit (default 50)
+    pub feed_max_limit: usize,
+    // BATCH_CONCURRENCY: how many entries of one /batch request are resolved at once (default 4).
+    // cache hits take a slot too, they just give it back straight away. the limit is per request,
+    // there's no global one: FEED_CONCURRENCY and POLL_CONCURRENCY bound /feed and the poller on
+    // their own, so github can see up to all of them at once. across every path, the in-flight
+    // guard makes concurrent misses for the same release share one fetch
+    pub batch_concurrency: usize,
+    // BATCH_MAX_REPOS: most entries a /batch request may ask for (default 50)
+    pub batch_max_repos: usize,
+    // POLL_CONCURRENCY: how many background refreshes may hit github at once (default 2)
+    pub poll_concurrency: usize,
+    // POLL_MIN_RATE_REMAINING: polls are skipped while the core rate limit has fewer requests
+    // left than this, so background refreshes never starve client traffic (default 500)
+    pub poll_min_rate_remaining: usize,
+    // ALLOWED_ORIGINS: comma separated origins browsers may call the api from (e.g.
+    // https://example.com), `*` allows any. unset sends no CORS headers
+    pub allowed_origins: Vec<String>,
+    // ADMIN_TOKEN: bearer token for admin only options like ?raw_release=true, unset disables them
+    pub admin_token: Option<String>,
+    // DEFAULT_AVATAR_URL: author image used when github gives an empty avatar url, unset leaves it empty
+    pub default_avatar_url: Option<String>,
+    // CHANNEL_ALIASES: operator defined channel names served at /:org/:repo/channel/:name,
+    // comma separated `name=tag` (e.g. stable=v1.2.3,lts=v1.0.9). names are case sensitive
+    pub channel_aliases: HashMap<String, String>,
+    // META_MAX_PULLS: how many linked pull requests ?meta=true looks up for milestone/labels,
+    // each one is a github call (default 10)
+    pub meta_max_pulls: usize,
+    // BREAKING_PATTERNS: comma separated heading labels that mark a breaking changes section,
+    // matched case insensitively at the start of a word in the heading (so not "non-breaking")
+    pub breaking_patterns: Vec<String>,
+    // HIGHLIGHT_PATTERNS: comma separated heading labels that mark a highlights section,
+    // matched like BREAKING_PATTERNS
+    pub highlight_patterns: Vec<String>,
+    // REQUIREMENT_PATTERNS: comma separated phrases marking a line as a runtime requirement for
+    // ?requirements=true, matched case insensitively anywhere in the line as long as a version number
+    // comes after it
+    pub requirement_patterns: Vec<String>,
+    // GITHUB_BASE_URI: api root for github enterprise server (e.g. https://github.example.com/api/v3),
+    // unset uses the public api.github.com
+    pub github_base_uri: Option<String>,
+    // GITHUB_API_VERSION: sent as X-GitHub-Api-Version on every github call (e.g. 2022-11-28),
+    // when unset no header is sent and github applies its default version
+    pub github_api_version: Option<String>,
+    // SUMMARY_MAX_LEN: ?summary=true text is cut at a word boundary past this many characters (default 200)
+    pub summary_max_len: usize,
+    // CACHE_TTL_SECS: how long a cached release is served before it's refetched (default 300)
+    pub cache_ttl: Duration,
+    // CACHE_TAG_TTL_SECS: ttl for releases looked up by tag, which only change when the notes are
+    // edited, so they can be kept much longer than latest (default 86400)
+    pub cache_tag_ttl: Duration,
+    // CACHE_TTL_JITTER_PCT: each entry's ttl is randomly stretched or shrunk by up to this
+    // percentage, so entries cached together (e.g. by the poller) don't all expire at once (default 10)
+    pub cache_ttl_jitter_pct: f64,
+    // MIN_LATEST_TTL_SECS: floor for ?max_age on latest/prerelease lookups (default 30).
+    // an entry is served when it's younger than CACHE_TTL_SECS (with jitter) and, if the client
+    // sent max_age, also younger than max(max_age, this floor). max_age can only ask for fresher
+    // data than the ttl, never keep an entry longer, and tag lookups take max_age as given
+    pub min_latest_ttl: Duration,
+    // ADAPTIVE_TTL: scale the cache ttl with load, keeping entries longer during request spikes
+    // to save github quota and refreshing sooner when it's quiet (default false). the base ttl is
+    //   clamp(CACHE_TTL_SECS * rate / ADAPTIVE_TTL_REFERENCE_RPS, ADAPTIVE_TTL_MIN_SECS, ADAPTIVE_TTL_MAX_SECS)
+    // where rate is release lookups per second over the last minute, so at the reference rate the
+    // ttl is CACHE_TTL_SECS, at twice the rate it doubles, and so on. jitter is applied after
+    pub adaptive_ttl: bool,
+    // ADAPTIVE_TTL_REFERENCE_RPS: request rate at which the adaptive ttl equals CACHE_TTL_SECS (default 1)
+    pub adaptive_ttl_reference_rps: f64,
+    // ADAPTIVE_TTL_MIN_SECS / ADAPTIVE_TTL_MAX_SECS: bounds for the adaptive ttl (default 60 and 1800)
+    pub adaptive_ttl_min: Duration,
+    pub adaptive_ttl_max: Duration,
+    // SEED_CACHE_FILE: json list of responses (as served by /:org/:repo) loaded into the cache at
+    // startup, for reproducible test runs. each entry may also carry the raw markdown `body`
+    // (needed by ?format=markdown|html, ?summary and ?title_from_body), `untitled` and `etag`.
+    // unset means no seeding
+    pub seed_cache_file: Option<String>,
+    // SEED_GRACE_SECS: seeded entries are served without refetching for this long (default 3600)
+    pub seed_grace: Duration,
+    // CACHE_MAX_AGE_PINNED_SECS: Cache-Control max-age for releases looked up by tag,
+    // their notes rarely change once published (default 86400)
+    pub cache_max_age_pinned: u64,
+    // CACHE_MAX_AGE_LATEST_SECS: max-age for latest/prerelease lookups, kept short so a new
+    // release shows up quickly downstream (default 60, 0 means no-store)
+    pub cache_max_age_latest: u64,
+    // CACHE_MAX_AGE_NOT_FOUND_SECS: max-age for 404s, so a repo that just got its first
+    // release isn't hidden for long (default 30, 0 means no-store)
+    pub cache_max_age_not_found: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct RepoName {
+    pub org: String,
+    pub repo: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct PollTarget {
+    pub org: String,
+    pub repo: String,
+    pub interval: Duration,
+}
+
+impl Config {
+    pub fn from_env() -> Self {
+        Config {
+            repo_meta_ttl: Duration::from_secs(env_or("REPO_META_TTL_SECS", 3600)),
+            poll_repos: parse_poll_repos(
+                &env::var("POLL_REPOS").unwrap_or_default(),
+                Duration::from_secs(env_or("POLL_INTERVAL_SECS", 300)),
+            ),
+            feed_repos: env::var("FEED_REPOS").unwrap_or_default()
+                .split(',')
+                .map(str::trim)
+                .filter(|entry| !entry.is_empty())
+                .filter_map(|entry| parse_repo_name(entry).or_else(|| {
+                    tracing::warn!("ignoring invalid FEED_REPOS entry: {:?}", entry);
+                    None
+                }))
+                .collect(),
+            feed_concurrency: env_or("FEED_CONCURRENCY", 4).max(1),
+            feed_max_repos: env_or("FEED_MAX_REPOS", 50),
+            feed_max_limit: env_or("FEED_MAX_LIMIT", 50).max(1),
+            batch_concurrency: env_or("BATCH_CONCURRENCY", 4).max(1),
+            batch_max_repos: env_or("BATCH_MAX_REPOS", 50),
+            poll_concurrency: env_or("POLL_CONCURRENCY", 2).max(1),
+            poll_min_rate_remaining: env_or("POLL_MIN_RATE_REMAINING", 500),
+            default_avatar_url: env::var("DEFAULT_AVATAR_URL").ok()
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty()),
+            channel_aliases: parse_channel_aliases(&env::var("CHANNEL_ALIASES").unwrap_or_default()),
+            meta_max_pulls: env_or("META_MAX_PULLS", 10),
+            allowed_origins: env_list("ALLOWED_ORIGINS", &[]),
+            admin_token: env::var("ADMIN_TOKEN").ok().filter(|t| !t.is_empty()),
+            github_base_uri: env::var("GITHUB_BASE_URI").ok()
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty()),
+            github_api_version: env::var("GITHUB_API_VERSION").ok()
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty()),
+            summary_max_len: env_or("SUMMARY_MAX_LEN", 200),
+            cache_ttl: Duration::from_secs(env_or("CACHE_TTL_SECS", 300)),
+            cache_tag_ttl: Duration::from_secs(env_or("CACHE_TAG_TTL_SECS", 86400)),
+            min_latest_ttl: Duration::from_secs(env_or("MIN_LATEST_TTL_SECS", 30)),
+            adaptive_ttl: env_or("ADAPTIVE_TTL", false),
+            adaptive_ttl_reference_rps: env_or("ADAPTIVE_TTL_REFERENCE_RPS", 1.0f64).max(0.001),
+            adaptive_ttl_min: Duration::from_secs(env_or("ADAPTIVE_TTL_MIN_SECS", 60)),
+            adaptive_ttl_max: Duration::from_secs(env_or("ADAPTIVE_TTL_MAX_SECS", 1800)),
+            seed_cache_file: env::var("SEED_CACHE_FILE").ok().filter(|f| !f.is_empty()),
+            seed_grace: Duration::from_secs(env_or("SEED_GRACE_SECS", 3600)),
+            cache_max_age_pinned: env_or("CACHE_MAX_AGE_PINNED_SECS", 86400),
+            cache_max_age_latest: env_or("CACHE_MAX_AGE_LATEST_SECS", 60),
+            cache_max_age_not_found: env_or("CACHE_MAX_AGE_NOT_FOUND_SECS", 30),
+            cache_ttl_jitter_pct: env_or("CACHE_TTL_JITTER_PCT", 10.0f64).clamp(0.0, 100.0),
+            highlight_patterns: env_list("HIGHLIGHT_PATTERNS", &["highlights", "key changes"]),
+            requirement_patterns: env_list("REQUIREMENT_PATTERNS", &["requires", "minimum supported", "requirement"]),
+            breaking_patterns: env_list("BREAKING_PATTERNS", &["breaking change", "⚠️ breaking", "breaking"]),
+        }
+    }
+}
+
+impl Config {
+    // ttl for a newly cached release given how it was looked up and the current request rate,
+    // with jitter applied. adaptive scaling only applies to lookups that move with new releases
+    pub fn release_ttl(&self, resolved_by: Lookup, requests_per_sec: f64) -> Duration {
+        let ttl = match self.adaptive_ttl {
+            _ if resolved_by == Lookup::Tag => self.cache_tag_ttl,
+            true => {
+                let min = self.adaptive_ttl_min.min(self.adaptive_ttl_max);
+                // scale in f64 seconds so a huge spike can't overflow a Duration
+                let scaled = self.cache_ttl.as_secs_f64() * requests_per_sec / self.adaptive_ttl_reference_rps;
+                Duration::from_secs_f64(scaled.clamp(min.as_secs_f64(), self.adaptive_ttl_max.as_secs_f64()))
+            },
+            false => self.cache_ttl
+        };
+        jitter(ttl, self.cache_ttl_jitter_pct)
+    }
+}
+
+// scale by a random factor in [1 - pct%, 1 + pct%]
+fn jitter(ttl: Duration, pct: f64) -> Duration {
+    if pct <= 0.0 {
+        return ttl;
+    }
+    // a freshly seeded hasher is plenty random for spreading out expiry times
+    let random = RandomState::new().build_hasher().finish() as f64 / u64::MAX as f64;
+    ttl.mul_f64(1.0 + pct / 100.0 * (2.0 * random - 1.0))
+}
+
+// BIND_ADDR (default 0.0.0.0) and PORT (default 4200), read at runtime so platforms that inject
+// PORT work. unlike other settings an invalid value is an error rather than a warning, since
+// silently listening somewhere else would be worse than not starting
+pub fn listen_addr() -> Result<SocketAddr, String> {
+    let host = env::var("BIND_ADDR").unwrap_or_else(|_| "0.0.0.0".to_string());
+    let ip: IpAddr = host.trim().parse()
+        .map_err(|_| format!("BIND_ADDR must be an ip address, got {:?}", host))?;
+    let port = match env::var("PORT") {
+        Ok(port) => port.trim().parse::<u16>()
+            .map_err(|_| format!("PORT must be a number between 0 and 65535, got {:?}", port))?,
+        Err(_) => 4200
+    };
+    Ok(SocketAddr::new(ip, port))
+}
+
+fn parse_poll_repos(raw: &str, default_interval: Duration) -> Vec<PollTarget> {
+    raw.split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .filter_map(|entry| {
+            let (name, interval) = match entry.split_once('=') {
+                Some((name, secs)) => match secs.trim().parse::<u64>() {
+                    Ok(secs) if secs > 0 => (name, Duration::from_secs(secs)),
+                    _ => {
+                        tracing::warn!("invalid poll interval for {}, using default", name);
+                        (name, default_interval)
+                    }
+                },
+                None => (entry, default_interval)
+            };
+            match parse_repo_name(name) {
+                Some(RepoName { org, repo }) => Some(PollTarget { org, repo, interval }),
+                None => {
+                    tracing::warn!("ignoring invalid POLL_REPOS entry: {:?}", entry);
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+fn parse_channel_aliases(raw: &str) -> HashMap<String, String> {
+    raw.split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .filter_map(|entry| match entry.split_once('=') {
+            Some((name, tag)) if !name.trim().is_empty() && !tag.trim().is_empty() => {
+                Some((name.trim().to_string(), tag.trim().to_string()))
+            },
+            _ => {
+                tracing::warn!("ignoring invalid CHANNEL_ALIASES entry: {:?}", entry);
+                None
+            }
+        })
+        .collect()
+}
+
+// `org/repo`
+pub fn parse_repo_name(raw: &str) -> Option<RepoName> {
+    match raw.trim().split_once('/') {
+        Some((org, repo)) if !org.is_empty() && !repo.is_empty() && !repo.contains('/') => Some(RepoName {
+            org: org.to_string(),
+            repo: repo.to_string(),
+        }),
+        _ => None
+    }
+}
+
+// comma separated, lowercased list
+fn env_list(name: &str, default: &[&str]) -> Vec<String> {
+    match env::var(name) {
+        Ok(value) => value.split(',')
+            .map(|v| v.trim().to_lowercase())
+            .filter(|v| !v.is_empty())
+            .collect(),
+        Err(_) => default.iter().map(|v| v.to_string()).collect()
+    }
+}
+
+// parse an env var, falling back to the default (with a warning) when it's missing or invalid
+fn env_or<T: FromStr>(name: &str, default: T) -> T {
+    match env::var(name) {
+        Ok(value) => value.trim().parse().unwrap_or_else(|_| {
+            tracing::warn!("invalid value for {}: {:?}, using default", name, value);
+            default
+        }),
+        Err(_) => default
+    }
+}
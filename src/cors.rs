@@ -0,0 +1,27 @@
+use axum::http::header::CONTENT_TYPE;
+use axum::http::{HeaderName, HeaderValue, Method};
+use tower_http::cors::{AllowOrigin, CorsLayer};
+
+use crate::config::Config;
+
+// cross origin access for browsers, only when ALLOWED_ORIGINS is set. preflights are answered
+// by the layer itself so they never reach a handler
+pub fn layer(config: &Config) -> Option<CorsLayer> {
+    if config.allowed_origins.is_empty() {
+        return None;
+    }
+    let origins = match config.allowed_origins.iter().any(|origin| origin == "*") {
+        true => AllowOrigin::any(),
+        false => AllowOrigin::list(config.allowed_origins.iter().filter_map(|origin| {
+            HeaderValue::from_str(origin).map_err(|_| {
+                tracing::warn!("ignoring invalid ALLOWED_ORIGINS entry: {:?}", origin);
+            }).ok()
+        }))
+    };
+    Some(CorsLayer::new()
+        .allow_origin(origins)
+        // POST is for /resolve and /feed, which take json bodies
+        .allow_methods([Method::GET, Method::POST])
+        .allow_headers([CONTENT_TYPE])
+        .expose_headers([HeaderName::from_static("x-request-id")]))
+}
@@ -0,0 +1,99 @@
+use axum::http::header::CONTENT_TYPE;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+
+use crate::error::ApiError;
+use crate::ApiResponse;
+
+const HEADER: [&str; 6] = ["tag", "published_at", "author", "title", "url", "items"];
+
+// how a list of releases is sent, from ?format=json|csv
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListFormat {
+    Json,
+    Csv,
+}
+
+impl ListFormat {
+    pub fn from_param(format: Option<&str>) -> Result<Self, ApiError> {
+        match format {
+            None | Some("json") => Ok(ListFormat::Json),
+            Some("csv") => Ok(ListFormat::Csv),
+            Some(_) => Err(ApiError::new(StatusCode::BAD_REQUEST, "invalid_format", "format must be json or csv"))
+        }
+    }
+}
+
+// one row per release with a header row, for spreadsheet imports. items counts the top level
+// items, headings included
+pub fn releases<'a>(releases: impl IntoIterator<Item = &'a ApiResponse>) -> Response {
+    ([(CONTENT_TYPE, "text/csv; charset=utf-8")], to_csv(releases)).into_response()
+}
+
+fn to_csv<'a>(releases: impl IntoIterator<Item = &'a ApiResponse>) -> String {
+    let mut csv = row(HEADER);
+    for res in releases {
+        let items = res.items.len().to_string();
+        csv.push_str(&row([
+            res.tag.as_str(),
+            res.published_at.as_deref().unwrap_or_default(),
+            res.author.as_ref().map_or("", |a| a.name.as_str()),
+            res.title.as_str(),
+            res.url.as_str(),
+            items.as_str(),
+        ]));
+    }
+    csv
+}
+
+// rfc 4180: fields with a comma, quote or line break are quoted, quotes doubled, lines end in crlf.
+// titles and author names come from any public repo, so a field a spreadsheet would run as a
+// formula (starting with = + - @, or a tab/cr) gets a leading ' to keep it text
+fn row<const N: usize>(fields: [&str; N]) -> String {
+    let fields: Vec<String> = fields.iter()
+        .map(|field| match field.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+            true => format!("'{}", field),
+            false => field.to_string()
+        })
+        .map(|field| match field.contains([',', '"', '\r', '\n']) {
+            true => format!("\"{}\"", field.replace('"', "\"\"")),
+            false => field
+        })
+        .collect();
+    fields.join(",") + "\r\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn release(title: &str, author: Option<&str>) -> ApiResponse {
+        serde_json::from_value(serde_json::json!({
+            "repo": "r",
+            "org": "o",
+            "title": title,
+            "latest": false,
+            "resolved_by": "list",
+            "prerelease": false,
+            "author": author.map(|name| serde_json::json!({"name": name, "image": ""})),
+            "tag": "v1",
+            "published_at": "2024-01-02T03:04:05+00:00",
+            "items": [{"category": "text", "text": "one"}, {"category": "text", "text": "two"}],
+            "url": "https://github.com/o/r/releases/tag/v1",
+        })).expect("invalid test release")
+    }
+
+    #[test]
+    fn csv_quotes_what_needs_quoting() {
+        assert_eq!(to_csv([&release("Fix \"quotes\", commas", None)]), concat!(
+            "tag,published_at,author,title,url,items\r\n",
+            "v1,2024-01-02T03:04:05+00:00,,\"Fix \"\"quotes\"\", commas\",https://github.com/o/r/releases/tag/v1,2\r\n",
+        ));
+        let formulas = [release("=HYPERLINK(\"https://x.y\")", Some("@evil")), release("+1", Some("-2"))];
+        assert_eq!(to_csv(&formulas), concat!(
+            "tag,published_at,author,title,url,items\r\n",
+            "v1,2024-01-02T03:04:05+00:00,'@evil,\"'=HYPERLINK(\"\"https://x.y\"\")\",https://github.com/o/r/releases/tag/v1,2\r\n",
+            "v1,2024-01-02T03:04:05+00:00,'-2,'+1,https://github.com/o/r/releases/tag/v1,2\r\n",
+        ));
+    }
+}
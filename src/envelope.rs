@@ -0,0 +1,103 @@
+use std::time::Duration;
+
+use axum::body::{to_bytes, Body, HttpBody};
+use axum::extract::Request;
+use axum::http::header::{CONTENT_LENGTH, CONTENT_TYPE};
+use axum::http::StatusCode;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use serde::de::IgnoredAny;
+use serde::Serialize;
+
+use crate::error::ApiError;
+use crate::logging::RequestId;
+use crate::ApiResponse;
+
+// responses larger than this, or of unknown size, are passed through unwrapped rather than buffered
+const MAX_ENVELOPE_BODY: usize = 16 * 1024 * 1024;
+
+// whether a response came from the cache and how old it was, set by handlers as a response extension
+#[derive(Debug, Clone, Copy)]
+pub struct Freshness {
+    pub cached: bool,
+    pub age: Duration,
+}
+
+impl Freshness {
+    pub fn of(res: &ApiResponse) -> Self {
+        Freshness {
+            cached: res.cache_age.is_some(),
+            age: res.cache_age.unwrap_or_default(),
+        }
+    }
+
+    // for responses merged from several releases: cached only if all of them were
+    pub fn oldest(self, other: Freshness) -> Self {
+        Freshness {
+            cached: self.cached && other.cached,
+            age: self.age.max(other.age),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Meta {
+    cached: bool,
+    // seconds since the data was fetched from github
+    age: u64,
+    request_id: Option<String>,
+    api_version: &'static str,
+}
+
+// with ?envelope=true, successful json responses become {meta: {...}, data: <original body>}.
+// errors and non-json responses such as 304s are passed through as they are
+pub async fn wrap(request: Request, next: Next) -> Response {
+    let wanted = request.uri().query().is_some_and(|query| {
+        url::form_urlencoded::parse(query.as_bytes()).any(|(k, v)| k == "envelope" && v == "true")
+    });
+    if !wanted {
+        return next.run(request).await;
+    }
+    let request_id = request.extensions().get::<RequestId>().map(|id| id.0.clone());
+
+    let response = next.run(request).await;
+    let is_json = response.headers().get(CONTENT_TYPE).is_some_and(|t| t.as_bytes().starts_with(b"application/json"));
+    if !response.status().is_success() || !is_json {
+        return response;
+    }
+
+    // decided before buffering, which consumes the body
+    if response.body().size_hint().upper().is_none_or(|size| size > MAX_ENVELOPE_BODY as u64) {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let freshness = parts.extensions.get::<Freshness>().copied();
+    let bytes = match to_bytes(body, MAX_ENVELOPE_BODY).await {
+        Ok(bytes) => bytes,
+        // the body is gone by now, so there's nothing left to pass through
+        Err(e) => {
+            tracing::error!("couldn't buffer response for envelope: {}", e);
+            return ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "envelope_failed", "couldn't wrap the response").into_response();
+        }
+    };
+    let meta = Meta {
+        cached: freshness.is_some_and(|f| f.cached),
+        age: freshness.map_or(0, |f| f.age.as_secs()),
+        request_id,
+        api_version: env!("CARGO_PKG_VERSION"),
+    };
+    // the body is spliced in as is rather than reparsed into a Value, so its field order survives
+    let meta = match serde_json::to_vec(&meta) {
+        Ok(meta) if serde_json::from_slice::<IgnoredAny>(&bytes).is_ok() => meta,
+        _ => return Response::from_parts(parts, Body::from(bytes))
+    };
+    let mut json = Vec::with_capacity(bytes.len() + meta.len() + 20);
+    json.extend_from_slice(b"{\"meta\":");
+    json.extend_from_slice(&meta);
+    json.extend_from_slice(b",\"data\":");
+    json.extend_from_slice(&bytes);
+    json.push(b'}');
+    parts.headers.remove(CONTENT_LENGTH);
+    Response::from_parts(parts, Body::from(json))
+}
@@ -0,0 +1,134 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use axum::http::header::RETRY_AFTER;
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::Serialize;
+
+// error responses carry a stable machine readable code next to the status
+#[derive(Debug, Clone)]
+pub struct ApiError {
+    pub status: StatusCode,
+    pub code: &'static str,
+    pub message: String,
+    // seconds, sent as Retry-After
+    pub retry_after: Option<u64>,
+}
+
+// github's documented minimum wait for secondary limits, for rate limited responses that didn't
+// say how long to wait (octocrab's errors don't carry the response headers, see github_retry_after)
+const RATE_LIMIT_RETRY_SECS: u64 = 60;
+
+#[derive(Serialize)]
+struct ErrorBody<'a> {
+    code: &'a str,
+    message: &'a str,
+}
+
+impl ApiError {
+    pub fn new(status: StatusCode, code: &'static str, message: impl Into<String>) -> Self {
+        ApiError {
+            status,
+            code,
+            message: message.into(),
+            retry_after: None,
+        }
+    }
+
+    // a rate limited error waits as long as github asked, when it said
+    pub fn with_retry_after(mut self, secs: Option<u64>) -> Self {
+        if self.retry_after.is_some() && secs.is_some() {
+            self.retry_after = secs;
+        }
+        self
+    }
+
+    // work out what a failed github call means for our caller, logging it on the way
+    pub fn from_github(error: octocrab::Error) -> Self {
+        match &error {
+            // primary limits come back as 403 "API rate limit exceeded", secondary ones as 403 or 429
+            octocrab::Error::GitHub { source, .. } if source.status_code == StatusCode::TOO_MANY_REQUESTS
+                || (source.status_code == StatusCode::FORBIDDEN && source.message.to_lowercase().contains("rate limit")) => {
+                tracing::warn!("github rate limit hit: {}", source.message);
+                let mut error = ApiError::new(StatusCode::TOO_MANY_REQUESTS, "rate_limited", "github rate limit exceeded, try again later");
+                error.retry_after = Some(RATE_LIMIT_RETRY_SECS);
+                error
+            },
+            octocrab::Error::GitHub { source, .. } if source.status_code == StatusCode::FORBIDDEN => {
+                // a valid token without the scope to read releases gets "Resource not accessible by ..."
+                if source.message.contains("Resource not accessible by") {
+                    tracing::warn!("github token lacks the scope to read this repo's releases, widen the PAT's scopes: {}", source.message);
+                    ApiError::new(StatusCode::FORBIDDEN, "insufficient_scope", "the configured github token can't read releases for this repo")
+                } else {
+                    tracing::error!("github refused the request: {}", source.message);
+                    ApiError::new(StatusCode::FORBIDDEN, "forbidden", source.message.clone())
+                }
+            },
+            octocrab::Error::GitHub { source, .. } if source.status_code == StatusCode::NOT_FOUND => {
+                tracing::info!("release not found: {}", source.message);
+                ApiError::new(StatusCode::NOT_FOUND, "not_found", "release not found")
+            },
+            // anything else (github errors, network failures, bad responses) is upstream's fault
+            _ => {
+                tracing::error!("failed to fetch release: {}", error);
+                ApiError::new(StatusCode::BAD_GATEWAY, "upstream_error", "couldn't fetch the release from github")
+            }
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = ErrorBody {
+            code: self.code,
+            message: &self.message,
+        };
+        let mut response = (self.status, Json(body)).into_response();
+        if let Some(secs) = self.retry_after {
+            response.headers_mut().insert(RETRY_AFTER, secs.into());
+        }
+        response
+    }
+}
+
+// how long a github response asks us to wait: retry-after (seconds) for secondary limits, or until
+// x-ratelimit-reset (a unix timestamp) once the primary quota is used up
+pub fn github_retry_after(headers: &HeaderMap) -> Option<u64> {
+    let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok()).and_then(|v| v.trim().parse::<u64>().ok());
+    if let Some(secs) = header("retry-after") {
+        return Some(secs);
+    }
+    match header("x-ratelimit-remaining") {
+        Some(0) => {
+            let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+            header("x-ratelimit-reset").map(|reset| reset.saturating_sub(now).max(1))
+        },
+        _ => None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::{HeaderName, HeaderValue};
+
+    use super::*;
+
+    #[test]
+    fn retry_after_comes_from_githubs_headers() {
+        let headers = |pairs: &[(&'static str, String)]| pairs.iter()
+            .map(|(name, value)| (HeaderName::from_static(name), HeaderValue::from_str(value).unwrap()))
+            .collect::<HeaderMap>();
+        assert_eq!(github_retry_after(&headers(&[("retry-after", "30".to_string())])), Some(30));
+        let reset = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() + 120;
+        let exhausted = headers(&[("x-ratelimit-remaining", "0".to_string()), ("x-ratelimit-reset", reset.to_string())]);
+        assert!(github_retry_after(&exhausted).is_some_and(|secs| (119..=120).contains(&secs)));
+        let remaining = headers(&[("x-ratelimit-remaining", "10".to_string()), ("x-ratelimit-reset", reset.to_string())]);
+        assert_eq!(github_retry_after(&remaining), None);
+
+        let limited = ApiError::new(StatusCode::TOO_MANY_REQUESTS, "rate_limited", "slow down");
+        assert_eq!(ApiError { retry_after: Some(60), ..limited.clone() }.with_retry_after(Some(5)).retry_after, Some(5));
+        assert_eq!(ApiError { retry_after: Some(60), ..limited.clone() }.with_retry_after(None).retry_after, Some(60));
+        assert_eq!(ApiError::new(StatusCode::NOT_FOUND, "not_found", "gone").with_retry_after(Some(5)).retry_after, None);
+    }
+}
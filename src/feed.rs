@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+use crate::cache_control::CacheClass;
+use crate::csv::{self, ListFormat};
+use crate::envelope::Freshness;
+use crate::config::{parse_repo_name, RepoName};
+use crate::error::ApiError;
+use crate::{resolve_release, truncate_text, ApiResponse, CacheState};
+
+// one release in the cross-repo feed
+#[derive(Serialize, Debug, Clone)]
+pub struct FeedEntry {
+    pub org: String,
+    pub repo: String,
+    pub tag: String,
+    pub title: String,
+    pub url: String,
+    pub prerelease: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub published_at: Option<String>,
+    pub summary: String,
+}
+
+#[derive(Deserialize)]
+pub struct FeedRequest {
+    pub repos: Vec<String>,
+}
+
+// latest releases across FEED_REPOS, newest first. ?format=csv sends one row per release instead of json
+pub async fn get_feed(
+    Query(params): Query<HashMap<String,String>>,
+    State(state): State<CacheState>
+) -> Result<Response, ApiError> {
+    let format = ListFormat::from_param(params.get("format").map(String::as_str))?;
+    let repos = state.config.feed_repos.clone();
+    Ok(feed(state, &params, format, repos).await)
+}
+
+// same as GET /feed for a posted list of `org/repo` names
+pub async fn post_feed(
+    Query(params): Query<HashMap<String,String>>,
+    State(state): State<CacheState>,
+    Json(body): Json<FeedRequest>
+) -> Result<Response, ApiError> {
+    let format = ListFormat::from_param(params.get("format").map(String::as_str))?;
+    if body.repos.len() > state.config.feed_max_repos {
+        return Err(ApiError::new(StatusCode::BAD_REQUEST, "too_many_repos",
+            format!("at most {} repos per feed", state.config.feed_max_repos)));
+    }
+    let repos = body.repos.iter()
+        .map(|name| parse_repo_name(name).ok_or_else(|| {
+            ApiError::new(StatusCode::BAD_REQUEST, "invalid_repo", format!("expected org/repo, got {:?}", name))
+        }))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(feed(state, &params, format, repos).await)
+}
+
+// resolve each repo's latest release through the cache (FEED_CONCURRENCY at a time), then keep the
+// ?limit= newest by published_at. repos that fail to resolve are left out rather than failing the feed
+async fn feed(state: CacheState, params: &HashMap<String,String>, format: ListFormat, repos: Vec<RepoName>) -> Response {
+    let limit = params.get("limit")
+        .and_then(|l| l.parse::<usize>().ok())
+        .unwrap_or(10)
+        .min(state.config.feed_max_limit);
+
+    let permits = Arc::new(Semaphore::new(state.config.feed_concurrency));
+    let mut tasks = JoinSet::new();
+    for RepoName { org, repo } in repos {
+        let (state, permits) = (state.clone(), permits.clone());
+        tasks.spawn(async move {
+            let _permit = permits.acquire().await.expect("feed semaphore closed");
+            resolve_release(&state, &org, &repo, None, None).await.map_err(|e| {
+                tracing::warn!(org = %org, repo = %repo, "leaving repo out of feed: {:?}", e);
+            })
+        });
+    }
+
+    let mut releases: Vec<ApiResponse> = vec![];
+    let mut freshness = Freshness { cached: true, age: Default::default() };
+    while let Some(result) = tasks.join_next().await {
+        if let Ok(Ok(res)) = result {
+            freshness = freshness.oldest(Freshness::of(&res));
+            releases.push(res);
+        }
+    }
+    // rfc3339 in utc sorts correctly as text, unpublished entries go last
+    releases.sort_by(|a, b| b.published_at.cmp(&a.published_at));
+    releases.truncate(limit);
+
+    let mut response = match format {
+        ListFormat::Json => Json(releases.into_iter().map(|res| FeedEntry {
+            summary: truncate_text(&res.lead, state.config.summary_max_len),
+            org: res.org,
+            repo: res.repo,
+            tag: res.tag,
+            title: res.title,
+            url: res.url,
+            prerelease: res.prerelease,
+            published_at: res.published_at,
+        }).collect::<Vec<_>>()).into_response(),
+        ListFormat::Csv => csv::releases(&releases)
+    };
+    response.extensions_mut().insert(CacheClass::Moving);
+    response.extensions_mut().insert(freshness);
+    response
+}
@@ -0,0 +1,89 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::Json;
+use serde::Serialize;
+
+use crate::error::ApiError;
+use crate::{flag, CacheState};
+
+// probes can hit us every few seconds, github only needs asking about the token this often
+const RATE_LIMIT_CHECK_TTL: Duration = Duration::from_secs(30);
+
+// the last answer from github's /rate_limit, failures are kept too so a bad token isn't rechecked on every probe
+pub struct RateLimitCheck {
+    result: Result<u64, String>,
+    checked_at: Instant,
+}
+
+#[derive(Serialize)]
+pub struct Health {
+    status: &'static str,
+    cached_releases: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rate_limit_remaining: Option<u64>,
+}
+
+#[derive(Serialize)]
+pub struct Ready {
+    status: &'static str,
+    rate_limit_remaining: u64,
+}
+
+// liveness, never fetches a release, ?rate_limit=true adds the remaining github quota
+pub async fn get_health(
+    Query(params): Query<HashMap<String,String>>,
+    State(state): State<CacheState>
+) -> Json<Health> {
+    let cached_releases = state.releases.lock().await.len();
+    let rate_limit_remaining = match flag(&params, "rate_limit") {
+        true => check_rate_limit(&state).await.ok(),
+        false => None
+    };
+    Json(Health {
+        status: "ok",
+        cached_releases,
+        rate_limit_remaining,
+    })
+}
+
+// readiness, confirms github accepts our token
+pub async fn get_ready(State(state): State<CacheState>) -> Result<Json<Ready>, ApiError> {
+    match check_rate_limit(&state).await {
+        Ok(remaining) => Ok(Json(Ready {
+            status: "ready",
+            rate_limit_remaining: remaining,
+        })),
+        Err(message) => Err(ApiError::new(StatusCode::SERVICE_UNAVAILABLE, "github_unavailable", message))
+    }
+}
+
+// remaining core requests, asking github at most once per RATE_LIMIT_CHECK_TTL
+async fn check_rate_limit(state: &CacheState) -> Result<u64, String> {
+    // held across the call so concurrent probes share one request
+    let mut last = state.rate_limit.lock().await;
+    if let Some(check) = last.as_ref().filter(|check| check.checked_at.elapsed() < RATE_LIMIT_CHECK_TTL) {
+        return check.result.clone();
+    }
+    // checking the rate limit doesn't count against it
+    state.metrics.github_call();
+    let result = match octocrab::instance().ratelimit().get().await {
+        Ok(limit) => Ok(limit.resources.core.remaining as u64),
+        // only github's own message goes back to the caller, transport errors carry a backtrace
+        Err(octocrab::Error::GitHub { source, .. }) => {
+            tracing::warn!("github rejected the rate limit check: {}", source.message);
+            Err(format!("github rejected the token check: {}", source.message))
+        },
+        Err(e) => {
+            tracing::warn!("github rate limit check failed: {}", e);
+            Err("couldn't reach github".to_string())
+        }
+    };
+    *last = Some(RateLimitCheck {
+        result: result.clone(),
+        checked_at: Instant::now(),
+    });
+    result
+}
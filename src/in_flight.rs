@@ -0,0 +1,48 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::{Mutex as AsyncMutex, OwnedMutexGuard};
+
+// per key locks so concurrent misses for the same release wait for one github fetch instead of
+// all making their own, while misses for different keys go ahead in parallel
+pub struct InFlight {
+    keys: Mutex<HashMap<String, Arc<AsyncMutex<()>>>>,
+}
+
+pub struct InFlightGuard<'a> {
+    owner: &'a InFlight,
+    key: String,
+    _lock: OwnedMutexGuard<()>,
+}
+
+impl InFlight {
+    pub fn new() -> Self {
+        InFlight {
+            keys: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // wait until nobody else holds the key, then hold it until the guard is dropped
+    pub async fn acquire(&self, key: String) -> InFlightGuard<'_> {
+        let lock = {
+            let mut keys = self.keys.lock().unwrap_or_else(|e| e.into_inner());
+            keys.entry(key.clone()).or_default().clone()
+        };
+        InFlightGuard {
+            owner: self,
+            key,
+            _lock: lock.lock_owned().await,
+        }
+    }
+}
+
+impl Drop for InFlightGuard<'_> {
+    fn drop(&mut self) {
+        let mut keys = self.owner.keys.lock().unwrap_or_else(|e| e.into_inner());
+        // the map and this guard hold the only references when nobody else is waiting,
+        // new waiters clone under the map lock so this can't race with them
+        if keys.get(&self.key).is_some_and(|lock| Arc::strong_count(lock) <= 2) {
+            keys.remove(&self.key);
+        }
+    }
+}
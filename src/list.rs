@@ -0,0 +1,104 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use axum::extract::{Path, Query, State};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+
+use crate::cache_control::CacheClass;
+use crate::csv::{self, ListFormat};
+use crate::envelope::Freshness;
+use crate::error::ApiError;
+use crate::{logging, ApiResponse, CacheState, Lookup};
+
+// github allows up to 100, but every release is parsed so keep pages small
+const MAX_PER_PAGE: u8 = 30;
+const DEFAULT_PER_PAGE: u8 = 10;
+
+// one page of a repo's releases, cached separately from single release lookups
+pub struct CachedList {
+    pub org: String,
+    pub repo: String,
+    pub page: u32,
+    pub per_page: u8,
+    pub releases: Vec<ApiResponse>,
+    pub expires_at: Instant,
+    pub fetched_at: Instant,
+}
+
+// a page of releases newest first, ?per_page= (at most 30, default 10) and ?page= (from 1).
+// drafts are left out. ?format=csv sends one row per release instead of json
+pub async fn get_releases(
+    Path((org, repo)): Path<(String,String)>,
+    Query(params): Query<HashMap<String,String>>,
+    State(state): State<CacheState>
+) -> Result<Response, ApiError> {
+    logging::record_repo(&org, &repo);
+    let format = ListFormat::from_param(params.get("format").map(String::as_str))?;
+    let per_page = params.get("per_page")
+        .and_then(|p| p.parse::<u32>().ok())
+        .map_or(DEFAULT_PER_PAGE, |p| p.clamp(1, MAX_PER_PAGE as u32) as u8);
+    let page = params.get("page").and_then(|p| p.parse::<u32>().ok()).unwrap_or(1).max(1);
+
+    let (mut releases, freshness) = {
+        let mut cache = state.release_lists.lock().await;
+        let now = Instant::now();
+        let hit = cache.find(|l| {
+            l.org == org && l.repo == repo && l.page == page && l.per_page == per_page && l.expires_at > now
+        });
+        match hit {
+            Some(list) => (list.releases.clone(), Freshness { cached: true, age: now.duration_since(list.fetched_at) }),
+            None => (vec![], Freshness { cached: false, age: Duration::ZERO })
+        }
+    };
+    if !freshness.cached {
+        state.metrics.github_call();
+        releases = fetch_page(&org, &repo, page, per_page).await?;
+        store_list(&state, &org, &repo, page, per_page, releases.clone()).await;
+    }
+
+    for release in releases.iter_mut() {
+        release.finalize(&state.config);
+    }
+    let mut response = match format {
+        ListFormat::Json => Json(releases).into_response(),
+        ListFormat::Csv => csv::releases(&releases)
+    };
+    response.extensions_mut().insert(CacheClass::Moving);
+    response.extensions_mut().insert(freshness);
+    Ok(response)
+}
+
+async fn fetch_page(org: &str, repo: &str, page: u32, per_page: u8) -> Result<Vec<ApiResponse>, ApiError> {
+    let releases = octocrab::instance().repos(org, repo).releases().list()
+        .per_page(per_page)
+        .page(page)
+        .send()
+        .await
+        .map_err(ApiError::from_github)?;
+    // github already returns them newest first
+    Ok(releases.items.into_iter()
+        .filter(|r| !r.draft)
+        .map(|r| ApiResponse::from_release(org, repo, Lookup::List, r))
+        .collect())
+}
+
+async fn store_list(state: &CacheState, org: &str, repo: &str, page: u32, per_page: u8, releases: Vec<ApiResponse>) {
+    let now = Instant::now();
+    let entry = CachedList {
+        org: org.to_string(),
+        repo: repo.to_string(),
+        page,
+        per_page,
+        releases,
+        expires_at: now + state.release_ttl(Lookup::List),
+        fetched_at: now,
+    };
+    let mut cache = state.release_lists.lock().await;
+    match cache.find(|l| l.org == org && l.repo == repo && l.page == page && l.per_page == per_page) {
+        Some(existing) => *existing = entry, // overwrite the expired entry
+        None => {
+            cache.insert(entry);
+        }
+    }
+}
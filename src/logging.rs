@@ -0,0 +1,185 @@
+use std::env;
+use std::fmt;
+use std::panic;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+
+use axum::extract::Request;
+use axum::http::HeaderValue;
+use axum::middleware::Next;
+use axum::response::Response;
+use serde_json::{Map, Value};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Instrument, Subscriber};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::fmt::format::Writer;
+use tracing_subscriber::fmt::time::{FormatTime, SystemTime};
+use tracing_subscriber::fmt::{FmtContext, FormatEvent, FormatFields};
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+static NEXT_REQUEST_ID: AtomicU64 = AtomicU64::new(1);
+
+// the id of the current request, available to inner middleware as a request extension
+#[derive(Debug, Clone)]
+pub struct RequestId(pub String);
+
+// LOG_FORMAT picks the output: `json` (one object per event, for log aggregators),
+// `compact`, or `pretty` (the default, for dev)
+pub fn init() {
+    let format = env::var("LOG_FORMAT").unwrap_or_default();
+    match format.as_str() {
+        "json" => tracing_subscriber::registry()
+            .with(LevelFilter::INFO)
+            .with(JsonSpanFields)
+            .with(tracing_subscriber::fmt::layer().event_format(JsonFormat))
+            .init(),
+        "compact" => tracing_subscriber::fmt().with_max_level(LevelFilter::INFO).compact().init(),
+        _ => {
+            tracing_subscriber::fmt().with_max_level(LevelFilter::INFO).pretty().init();
+            if !format.is_empty() && format != "pretty" {
+                tracing::warn!("unknown LOG_FORMAT {:?}, using pretty", format);
+            }
+        }
+    }
+    // panics caught while building items (and any others) are events like the rest, rather than
+    // the default hook's unformatted line on stderr. with RUST_BACKTRACE set the previous hook still
+    // runs too, so crashes keep their backtrace
+    let previous = panic::take_hook();
+    let backtrace = env::var_os("RUST_BACKTRACE").is_some_and(|v| v != "0");
+    panic::set_hook(Box::new(move |info| {
+        let location = info.location().map(|l| format!("{}:{}", l.file(), l.line())).unwrap_or_default();
+        tracing::error!(location = %location, "panicked: {}", info.payload_as_str().unwrap_or("unknown panic"));
+        if backtrace {
+            previous(info);
+        }
+    }));
+}
+
+// wrap each request in a span carrying its id (echoed back in x-request-id),
+// handlers fill in org/repo once they know them
+pub async fn request_span(mut request: Request, next: Next) -> Response {
+    // reuse an id handed to us by a proxy so logs can be correlated across services
+    let id = request.headers().get("x-request-id")
+        .and_then(|v| v.to_str().ok())
+        .filter(|v| !v.is_empty() && v.len() <= 64)
+        .map(str::to_string)
+        .unwrap_or_else(|| NEXT_REQUEST_ID.fetch_add(1, Ordering::Relaxed).to_string());
+
+    request.extensions_mut().insert(RequestId(id.clone()));
+
+    let span = tracing::info_span!(
+        "request",
+        request_id = %id,
+        method = %request.method(),
+        path = %request.uri().path(),
+        org = tracing::field::Empty,
+        repo = tracing::field::Empty,
+    );
+    async move {
+        let start = Instant::now();
+        let mut response = next.run(request).await;
+        tracing::info!(
+            status = response.status().as_u16(),
+            elapsed_ms = start.elapsed().as_millis() as u64,
+            "request finished"
+        );
+        if let Ok(value) = HeaderValue::from_str(&id) {
+            response.headers_mut().insert("x-request-id", value);
+        }
+        response
+    }.instrument(span).await
+}
+
+// tag the current request span with the repo it's for
+pub fn record_repo(org: &str, repo: &str) {
+    let span = tracing::Span::current();
+    span.record("org", org);
+    span.record("repo", repo);
+}
+
+// span fields captured as json, stored on the span so every event inside it can include them
+struct JsonFields(Map<String, Value>);
+
+struct JsonVisitor<'a>(&'a mut Map<String, Value>);
+
+impl Visit for JsonVisitor<'_> {
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.0.insert(field.name().to_string(), Value::from(value));
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().to_string(), Value::from(value));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().to_string(), Value::from(value));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().to_string(), Value::from(value));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), Value::from(value));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0.insert(field.name().to_string(), Value::from(format!("{:?}", value)));
+    }
+}
+
+struct JsonSpanFields;
+
+impl<S> Layer<S> for JsonSpanFields
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let mut fields = Map::new();
+        attrs.record(&mut JsonVisitor(&mut fields));
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(JsonFields(fields));
+        }
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            if let Some(JsonFields(fields)) = span.extensions_mut().get_mut::<JsonFields>() {
+                values.record(&mut JsonVisitor(fields));
+            }
+        }
+    }
+}
+
+struct JsonFormat;
+
+impl<S, N> FormatEvent<S, N> for JsonFormat
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(&self, ctx: &FmtContext<'_, S, N>, mut writer: Writer<'_>, event: &Event<'_>) -> fmt::Result {
+        let mut timestamp = String::new();
+        SystemTime.format_time(&mut Writer::new(&mut timestamp))?;
+
+        let mut object = Map::new();
+        object.insert("timestamp".to_string(), Value::from(timestamp));
+        object.insert("level".to_string(), Value::from(event.metadata().level().as_str()));
+        object.insert("target".to_string(), Value::from(event.metadata().target()));
+        // outer spans first so the innermost value wins if a field name repeats
+        if let Some(scope) = ctx.event_scope() {
+            for span in scope.from_root() {
+                if let Some(JsonFields(fields)) = span.extensions().get::<JsonFields>() {
+                    object.extend(fields.clone());
+                }
+            }
+        }
+        event.record(&mut JsonVisitor(&mut object));
+
+        writeln!(writer, "{}", Value::Object(object))
+    }
+}
@@ -1,7 +1,9 @@
-use std::any::Any;
 use std::collections::{HashMap, VecDeque};
 use std::error::Error;
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use axum::{
     http::StatusCode,
@@ -9,35 +11,200 @@ use axum::{
     Router, routing::get,
 };
 use axum::extract::{Path, Query, State};
+use axum::http::{HeaderMap, HeaderName, HeaderValue};
+use axum::http::header::{AUTHORIZATION, CACHE_CONTROL, CONTENT_TYPE, ETAG, IF_NONE_MATCH};
+use axum::middleware;
+use axum::response::{IntoResponse, Response};
 use dotenvy_macro::dotenv;
 use markdown::mdast::Node;
 use markdown::ParseOptions;
-use octocrab::Octocrab;
-use serde::Serialize;
+use octocrab::models::repos::Release;
+use octocrab::{FromResponse, Octocrab};
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, CONTROLS};
+use ring::digest;
+use serde::{Deserialize, Serialize};
+use subtle::ConstantTimeEq;
 use tokio::sync::Mutex;
 use uluru::LRUCache;
+use url::Url;
 
-const GITHUB_PAT: &'static str = dotenv!("GITHUB_AT");
+use crate::cache_control::CacheClass;
+use crate::config::Config;
+use crate::envelope::Freshness;
+use crate::error::ApiError;
+use crate::in_flight::InFlight;
+use crate::metrics::Metrics;
+use crate::request_rate::RequestRate;
 
-type CacheState = Arc<Mutex<LRUCache<ApiResponse,8192>>>;
+mod batch;
+mod cache_control;
+mod config;
+mod cors;
+mod csv;
+mod envelope;
+mod error;
+mod feed;
+mod health;
+mod in_flight;
+mod list;
+mod logging;
+mod metrics;
+mod poller;
+mod request_rate;
+mod seed;
+
+const GITHUB_PAT: &str = dotenv!("GITHUB_AT");
+
+type CacheState = Arc<AppState>;
+
+// entries are boxed so the cache itself stays small enough to build on the stack
+type ReleaseCache = LRUCache<Box<CachedRelease>,8192>;
+
+// what a cached release answers: the same org/repo looked up the same way, so a pinned tag that
+// happens to be the latest release is still a separate entry from latest
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReleaseKey {
+    pub org: String,
+    pub repo: String,
+    pub lookup: Lookup,
+    // only kept for Lookup::Tag, the other lookups answer whatever release they resolve to
+    pub tag: Option<String>,
+}
+
+// which lookup produced a release, sent as resolved_by
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Lookup {
+    Latest,
+    // latest including prereleases
+    LatestAny,
+    Prerelease,
+    Tag,
+    // a page of /:org/:repo/releases
+    List,
+}
+
+impl ReleaseKey {
+    pub fn new(org: &str, repo: &str, lookup: Lookup, tag: &str) -> Self {
+        ReleaseKey {
+            org: org.to_string(),
+            repo: repo.to_string(),
+            lookup,
+            tag: Some(tag.to_string()).filter(|_| lookup == Lookup::Tag),
+        }
+    }
+
+    fn of(response: &ApiResponse) -> Self {
+        ReleaseKey::new(&response.org, &response.repo, response.resolved_by, &response.tag)
+    }
+}
+
+pub struct CachedRelease {
+    pub key: ReleaseKey,
+    pub response: ApiResponse,
+    // entries past this are treated as a miss and refetched
+    pub expires_at: Instant,
+    // when the release was fetched, for ?max_age
+    pub fetched_at: Instant,
+}
+
+pub struct AppState {
+    releases: Mutex<ReleaseCache>,
+    // repo metadata is cached separately since it changes far less often than releases
+    repo_meta: Mutex<LRUCache<RepoMeta,1024>>,
+    // milestone/labels inferred per release, these cost several github calls so are kept a while
+    release_meta: Mutex<LRUCache<ReleaseMeta,1024>>,
+    // annotated tag messages, effectively immutable once pushed
+    tag_messages: Mutex<LRUCache<TagMessage,1024>>,
+    // pages of /:org/:repo/releases
+    release_lists: Mutex<LRUCache<list::CachedList,256>>,
+    // release fetches currently waiting on github, by lookup
+    in_flight: InFlight,
+    // release lookups per second, for ADAPTIVE_TTL
+    request_rate: RequestRate,
+    // last github token check, for /ready and /health
+    rate_limit: Mutex<Option<health::RateLimitCheck>>,
+    metrics: Metrics,
+    config: Config,
+}
+
+impl AppState {
+    // ttl for a newly cached release, scaled with load when ADAPTIVE_TTL is on
+    pub fn release_ttl(&self, resolved_by: Lookup) -> Duration {
+        self.config.release_ttl(resolved_by, self.request_rate.per_sec())
+    }
+}
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
+    logging::init();
+    let config = Config::from_env();
+    // checked up front so a bad PORT fails before we do any work
+    let listen_addr = config::listen_addr()?;
+    if let Some(base_uri) = &config.github_base_uri {
+        match Url::parse(base_uri) {
+            Ok(url) if matches!(url.scheme(), "http" | "https") && url.has_host() => {},
+            _ => return Err(format!("GITHUB_BASE_URI must be an http(s) url, got {:?}", base_uri).into())
+        }
+    }
+
     // set our default instance to use github PAT
-    let crab = Octocrab::builder()
-        .personal_token(GITHUB_PAT.to_string())
-        .build()?;
-    octocrab::initialise(crab);
+    let mut builder = Octocrab::builder()
+        .personal_token(GITHUB_PAT.to_string());
+    // github enterprise server, e.g. https://github.example.com/api/v3
+    if let Some(base_uri) = &config.github_base_uri {
+        builder = builder.base_uri(base_uri.as_str())?;
+        tracing::info!("using github api at {}", base_uri);
+    }
+    // pin the REST API version when configured, otherwise github uses its current default
+    if let Some(version) = &config.github_api_version {
+        builder = builder.add_header(HeaderName::from_static("x-github-api-version"), version.clone());
+    }
+    octocrab::initialise(builder.build()?);
+
+    let state: CacheState = Arc::new(AppState {
+        releases: Mutex::new(LRUCache::new()),
+        repo_meta: Mutex::new(LRUCache::new()),
+        release_meta: Mutex::new(LRUCache::new()),
+        tag_messages: Mutex::new(LRUCache::new()),
+        release_lists: Mutex::new(LRUCache::new()),
+        in_flight: InFlight::new(),
+        request_rate: RequestRate::new(),
+        rate_limit: Mutex::new(None),
+        metrics: Metrics::new(),
+        config,
+    });
 
-    let state: CacheState = Arc::new(Mutex::new(LRUCache::new()));
+    seed::load(&state).await;
 
+    // keep repos without webhooks fresh independent of client traffic
+    poller::spawn(state.clone());
 
-    let app = Router::new()
+    let mut app = Router::new()
+        .route("/health", get(health::get_health))
+        .route("/ready", get(health::get_ready))
+        .route("/metrics", get(metrics::get_metrics))
         .route("/:org/:repo", get(get_release_notes))
+        .route("/:org/:repo/badge", get(get_badge))
+        .route("/:org/:repo/prerelease", get(get_prerelease))
+        .route("/:org/:repo/releases", get(list::get_releases))
+        .route("/:org/:repo/channel/:name", get(get_channel))
         .route("/force/:org/:repo", get(force_refresh))
+        .route("/resolve", get(resolve_url_query).post(resolve_url_body))
+        .route("/feed", get(feed::get_feed).post(feed::post_feed))
+        .route("/batch", get(batch::get_batch).post(batch::post_batch))
+        .layer(middleware::from_fn(envelope::wrap))
+        .layer(middleware::from_fn_with_state(state.clone(), cache_control::apply))
+        .layer(middleware::from_fn_with_state(state.clone(), metrics::count_status));
+    if let Some(cors) = cors::layer(&state.config) {
+        app = app.layer(cors);
+    }
+    let app = app
+        .layer(middleware::from_fn(logging::request_span))
         .with_state(state);
 
-    let listener = tokio::net::TcpListener::bind("0.0.0.0:4200").await?;
+    let listener = tokio::net::TcpListener::bind(listen_addr).await?;
+    tracing::info!("listening on {}", listen_addr);
 
     axum::serve(listener, app).await?;
 
@@ -48,242 +215,1965 @@ async fn force_refresh(
     Path((org, repo)): Path<(String,String)>,
     Query(params): Query<HashMap<String,String>>,
     State(state): State<CacheState>
-) -> StatusCode {
-
-    let mut cache = state.lock().await;
-    let is_latest = params.get("tag").is_none()
-        || params.get("tag").is_some_and(|s| s.as_str() == "latest");
-    let tag = params.get("tag");
-    let octocrab = octocrab::instance();
-    let repos = octocrab.repos(org.clone(), repo.clone());
-    let releases = repos.releases();
-
-    let release = match tag {
-        Some(tag) => releases.get_by_tag(tag).await.map_err(|e| {
-            eprintln!("{}", e);
-            StatusCode::NOT_FOUND
-        }),
-        _ => releases.get_latest().await.map_err(|e| {
-            eprintln!("{}", e);
-            StatusCode::NOT_FOUND
-        })
+) -> Result<Response, ApiError> {
+    logging::record_repo(&org, &repo);
+    let tag = requested_tag(&params);
+    // always goes to github, the cache is only written
+    state.metrics.github_call();
+    // sent without an etag, so github always answers in full
+    let response = match fetch_release(&org, &repo, tag, None).await? {
+        Fetched::Modified(response) => *response,
+        Fetched::NotModified => return Err(ApiError::new(StatusCode::BAD_GATEWAY, "upstream_error", "github sent 304 for an unconditional request"))
     };
-    match release {
-        Ok(release) => {
-            cache.insert(ApiResponse {
-                repo,
-                org,
-                latest: is_latest,
-                title: release.name.unwrap_or(release.tag_name.clone()),
-                author: release.author.map(|a| AuthorInfo {
-                    name: a.login,
-                    image: a.avatar_url.to_string()
-                }),
-                tag: release.tag_name,
-                items: Item::from_list(release.body),
-                url: release.html_url.to_string(),
-            });
-            StatusCode::OK
+    let ttl = state.release_ttl(response.resolved_by);
+    store_release(&mut *state.releases.lock().await, response.clone(), ttl);
+
+    // the refreshed release, rendered like /:org/:repo, but never cached downstream so the next
+    // force goes to github again
+    let mut rendered = render_release(&state, &params, response).await;
+    rendered.headers_mut().insert(CACHE_CONTROL, HeaderValue::from_static("no-store"));
+    Ok(rendered)
+}
+
+
+async fn get_release_notes(
+    Path((org, repo)): Path<(String,String)>,
+    Query(params): Query<HashMap<String,String>>,
+    State(state): State<CacheState>,
+    headers: HeaderMap
+) -> Result<Response, ApiError> {
+    let tag = requested_tag(&params);
+    let raw_release = flag(&params, "raw_release");
+    let res = if raw_release {
+        if !is_admin(&state.config, &headers) {
+            return Err(ApiError::new(StatusCode::FORBIDDEN, "forbidden", "raw_release needs the admin token"));
+        }
+        // skip the cache so the raw data is exactly what we parsed from
+        logging::record_repo(&org, &repo);
+        state.metrics.github_call();
+        let (release, etag) = fetch_github_release(&org, &repo, tag, None).await?
+            .ok_or_else(|| ApiError::new(StatusCode::BAD_GATEWAY, "upstream_error", "github sent 304 for an unconditional request"))?;
+        let raw = serde_json::to_value(&release).ok();
+        let mut res = ApiResponse::from_release(&org, &repo, resolution(tag), release);
+        res.etag = etag;
+        store_release(&mut *state.releases.lock().await, res.clone(), state.release_ttl(resolution(tag)));
+        res.raw_release = raw;
+        res
+    } else if tag.is_none() && flag(&params, "include_prerelease") {
+        // github's latest never includes prereleases, some projects only ever publish those
+        let fetch = |_| fetch_newest(&state, &org, &repo, Lookup::LatestAny, |r| !r.draft);
+        resolve_cached(&state, &org, &repo, Lookup::LatestAny, None, requested_max_age(&params), fetch).await?
+    } else {
+        resolve_release(&state, &org, &repo, tag, requested_max_age(&params)).await?
+    };
+
+    let mut rendered = render_release(&state, &params, res).await;
+    // the raw release was asked for with the admin token, so no shared cache may keep it
+    if raw_release {
+        rendered.headers_mut().insert(CACHE_CONTROL, HeaderValue::from_static("private, no-store"));
+    }
+    Ok(rendered)
+}
+
+// apply the per-request options to a resolved release
+async fn render_release(state: &AppState, params: &HashMap<String,String>, res: ApiResponse) -> Response {
+    let class = CacheClass::for_resolution(res.resolved_by);
+    let freshness = Freshness::of(&res);
+    let mut response = render_options(state, params, res).await;
+    response.extensions_mut().insert(class);
+    response.extensions_mut().insert(freshness);
+    response
+}
+
+async fn render_options(state: &AppState, params: &HashMap<String,String>, mut res: ApiResponse) -> Response {
+    if flag(params, "canonical") {
+        return Json(CanonicalRelease::from(&res)).into_response();
+    }
+    // the notes as written, or rendered by the markdown crate, which escapes raw html and drops
+    // unsafe urls (javascript: etc.) by default
+    match params.get("format").map(String::as_str) {
+        None | Some("json") => {},
+        Some("markdown") => return ([(CONTENT_TYPE, "text/markdown; charset=utf-8")], res.body).into_response(),
+        Some("html") => return match markdown::to_html_with_options(&res.body, &markdown::Options::gfm()) {
+            Ok(html) => ([(CONTENT_TYPE, "text/html; charset=utf-8")], html).into_response(),
+            Err(e) => {
+                tracing::error!("failed to render release notes: {}", e);
+                ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "render_failed", "couldn't render the release notes").into_response()
+            }
         },
-        _ => StatusCode::INTERNAL_SERVER_ERROR
+        Some(_) => {
+            return ApiError::new(StatusCode::BAD_REQUEST, "invalid_format", "format must be json, markdown or html").into_response();
+        }
+    }
+    // ?since_hash compares the content of the notes, not which release they belong to, so an
+    // edited body counts as a change and an identical body on another release doesn't
+    if !res.body_hash.is_empty() && params.get("since_hash").is_some_and(|hash| hash.eq_ignore_ascii_case(&res.body_hash)) {
+        return (StatusCode::NOT_MODIFIED, [("x-body-hash", res.body_hash)]).into_response();
+    }
+    // expand footnote markers where they're referenced, for surfaces that can't link to a footnote list
+    if params.get("footnotes").is_some_and(|mode| mode == "inline") {
+        let footnotes = std::mem::take(&mut res.footnotes);
+        inline_footnotes(&mut res.items, &footnotes);
+    }
+    // every image goes, standalone ones and those inside links, in nested details groups too
+    if flag(params, "strip_images") {
+        strip_images(&mut res.items);
+    }
+    if flag(params, "title_from_body") {
+        title_from_body(&mut res);
+    }
+    res.finalize(&state.config);
+    // after finalize so the item carries the fallback avatar, the ids are reassigned to cover it
+    if flag(params, "author_as_item") {
+        append_author_item(&mut res);
+        Item::assign_ids(&mut res.items, "");
+    }
+    if flag(params, "toc") {
+        res.toc = Some(Item::table_of_contents(&res.items));
+    }
+    if flag(params, "summary") {
+        res.summary = Some(truncate_text(&res.lead, state.config.summary_max_len));
+    }
+    if flag(params, "breaking") {
+        res.breaking = Some(Item::section(&res.items, &state.config.breaking_patterns));
+    }
+    if flag(params, "highlights") {
+        res.highlights = Some(Item::section(&res.items, &state.config.highlight_patterns));
+    }
+    if flag(params, "requirements") {
+        res.requirements = Some(Item::requirements(&res.items, &state.config.requirement_patterns));
+    }
+    if flag(params, "meta") {
+        let meta = get_release_meta(state, &res).await;
+        res.milestone = meta.milestone;
+        res.labels = Some(meta.labels);
     }
+    if flag(params, "include_tag_message") {
+        res.tag_message = get_tag_message(state, &res.org, &res.repo, &res.tag).await;
+    }
+    if flag(params, "repo_meta") {
+        if let Some(meta) = get_repo_meta(state, &res.org, &res.repo).await {
+            res.homepage = meta.homepage;
+            res.default_branch = meta.default_branch;
+            res.stars = meta.stars;
+        }
+    }
+    Json(res).into_response()
 }
 
+fn strip_images(items: &mut Vec<Item>) {
+    items.retain(|item| item.category != "image");
+    for item in items.iter_mut() {
+        while let Some(start) = item.text.find("<img ") {
+            let end = item.text[start..].find('>').map_or(item.text.len(), |i| start + i + 1);
+            item.text.replace_range(start..end, "");
+        }
+        // a linked image leaves an empty anchor behind
+        let mut from = 0;
+        while let Some(close) = item.text[from..].find("></a>").map(|i| from + i) {
+            match item.text[..close].rfind("<a ") {
+                Some(open) if !item.text[open..close].contains('>') => item.text.replace_range(open..close + 5, ""),
+                _ => from = close + 5
+            }
+        }
+    }
+    items.retain(|item| !item.text.trim().is_empty() || item.items.is_some() || item.heading_level().is_some()
+        || item.category == "author");
+    for item in items {
+        if let Some(children) = item.items.as_mut() {
+            strip_images(children);
+        }
+    }
+}
 
-async fn get_release_notes(
+// some projects leave the name blank and open the notes with the real title as a heading.
+// a release with a name keeps it, and one without a leading heading keeps the tag
+fn title_from_body(res: &mut ApiResponse) {
+    if res.untitled && res.items.first().is_some_and(|i| i.heading_level().is_some()) {
+        res.title = plain_text(&res.items.remove(0).text);
+    }
+}
+
+// attribution as the last item, for clients that only render items
+fn append_author_item(res: &mut ApiResponse) {
+    if let Some(author) = &res.author {
+        let mut item = Item::new("author", author.name.clone());
+        item.url = Some(author.url.clone().unwrap_or_else(|| format!("https://github.com/{}", author.name)));
+        item.image = Some(author.image.clone());
+        res.items.push(item);
+    }
+}
+
+// replace each `[^label]` marker with ` (see: definition text)`
+fn inline_footnotes(items: &mut [Item], footnotes: &[Footnote]) {
+    for item in items {
+        for footnote in footnotes {
+            item.text = item.text.replace(&footnote.marker(), &format!(" (see: {})", escape_html(&footnote.text)));
+        }
+        if let Some(children) = item.items.as_mut() {
+            inline_footnotes(children, footnotes);
+        }
+    }
+}
+
+// shields.io endpoint badge for the latest (or ?tag=) release, served from the cache like the notes
+async fn get_badge(
     Path((org, repo)): Path<(String,String)>,
     Query(params): Query<HashMap<String,String>>,
     State(state): State<CacheState>
-) -> Result<Json<ApiResponse>, StatusCode> {
-    let release: Result<ApiResponse,StatusCode> = {
+) -> Result<Response, ApiError> {
+    let release = resolve_release(&state, &org, &repo, requested_tag(&params), requested_max_age(&params)).await?;
+    let class = CacheClass::for_resolution(release.resolved_by);
+    let mut response = Json(Badge {
+        schema_version: 1,
+        label: "release".to_string(),
+        message: release.tag,
+        color: if release.prerelease { "orange" } else { "blue" }.to_string(),
+    }).into_response();
+    response.extensions_mut().insert(class);
+    Ok(response)
+}
 
-        let mut cache = state.lock().await;
+#[derive(Deserialize)]
+pub struct ResolveRequest {
+    pub url: String,
+}
 
-        // if the 'tag' param is nothing or the literal "latest" then fetch latest
-        let fetch_latest = params.get("tag").is_none()
-            || params.get("tag").is_some_and(|s| s.as_str() == "latest");
-        let tag = params.get("tag");
+// same response as /:org/:repo, for clients holding a full release url
+async fn resolve_url_body(
+    Query(params): Query<HashMap<String,String>>,
+    State(state): State<CacheState>,
+    Json(body): Json<ResolveRequest>
+) -> Result<Response, ApiError> {
+    resolve_release_url(&state, &params, &body.url).await
+}
 
-        let result = match cache.find(|res| res.org == org && res.repo == repo && (fetch_latest == res.latest || tag.is_some_and(|t| t == &res.tag))) {
-            Some(release) => Ok::<ApiResponse,StatusCode>(release.clone()),
-            None => {
-                let octocrab = octocrab::instance();
-                let repos = octocrab.repos(org.clone(), repo.clone());
-                let releases = repos.releases();
-
-                let release = match tag {
-                    Some(tag) => releases.get_by_tag(tag).await.map_err(|e| {
-                        eprintln!("{}", e);
-                        StatusCode::NOT_FOUND
-                    })?,
-                    _ => releases.get_latest().await.map_err(|e| {
-                        eprintln!("{}", e);
-                        StatusCode::NOT_FOUND
-                    })?
-                };
-                let response = ApiResponse {
-                    repo,
-                    org,
-                    latest: fetch_latest,
-                    title: release.name.unwrap_or(release.tag_name.clone()),
-                    author: release.author.map(|a| AuthorInfo {
-                        name: a.login,
-                        image: a.avatar_url.to_string()
-                    }),
-                    tag: release.tag_name,
-                    items: Item::from_list(release.body),
-                    url: release.html_url.to_string(),
-                };
-                cache.insert(response.clone()); // actually put in cache
-                Ok(response)
+async fn resolve_url_query(
+    Query(params): Query<HashMap<String,String>>,
+    State(state): State<CacheState>
+) -> Result<Response, ApiError> {
+    let url = params.get("url").ok_or_else(|| ApiError::new(StatusCode::BAD_REQUEST, "invalid_url", "missing url"))?;
+    resolve_release_url(&state, &params, url).await
+}
+
+async fn resolve_release_url(state: &AppState, params: &HashMap<String,String>, url: &str) -> Result<Response, ApiError> {
+    let (org, repo, tag) = parse_release_url(url).ok_or_else(|| {
+        ApiError::new(StatusCode::BAD_REQUEST, "invalid_url", "expected a github.com/<org>/<repo>/releases/tag/<tag> url")
+    })?;
+    let res = resolve_release(state, &org, &repo, tag.as_deref(), requested_max_age(params)).await?;
+    Ok(render_release(state, params, res).await)
+}
+
+// split github.com/<org>/<repo>/releases/tag/<tag> (or .../releases/latest) into its parts,
+// the scheme is optional
+fn parse_release_url(raw: &str) -> Option<(String, String, Option<String>)> {
+    let raw = raw.trim();
+    let url = match raw.contains("://") {
+        true => Url::parse(raw),
+        false => Url::parse(&format!("https://{}", raw))
+    }.ok()?;
+    if !matches!(url.scheme(), "http" | "https") || !matches!(url.host_str(), Some("github.com" | "www.github.com")) {
+        return None;
+    }
+    let segments: Vec<String> = url.path_segments()?
+        .filter(|s| !s.is_empty())
+        .map(|s| percent_decode_str(s).decode_utf8_lossy().into_owned())
+        .collect();
+    match segments.as_slice() {
+        [org, repo, releases, rest @ ..] if releases == "releases" => match rest {
+            [latest] if latest == "latest" => Some((org.clone(), repo.clone(), None)),
+            // tags can contain slashes
+            [kind, tag @ ..] if kind == "tag" && !tag.is_empty() => Some((org.clone(), repo.clone(), Some(tag.join("/")))),
+            _ => None
+        },
+        _ => None
+    }
+}
+
+// newest prerelease, cached separately from the stable latest
+async fn get_prerelease(
+    Path((org, repo)): Path<(String,String)>,
+    Query(params): Query<HashMap<String,String>>,
+    State(state): State<CacheState>
+) -> Result<Response, ApiError> {
+    let fetch = |_| fetch_newest(&state, &org, &repo, Lookup::Prerelease, |r| r.prerelease && !r.draft);
+    let res = resolve_cached(&state, &org, &repo, Lookup::Prerelease, None, requested_max_age(&params), fetch).await?;
+    Ok(render_release(&state, &params, res).await)
+}
+
+// release for a named channel, mapped to a tag by CHANNEL_ALIASES
+async fn get_channel(
+    Path((org, repo, name)): Path<(String,String,String)>,
+    Query(params): Query<HashMap<String,String>>,
+    State(state): State<CacheState>
+) -> Result<Response, ApiError> {
+    let tag = state.config.channel_aliases.get(&name)
+        .ok_or_else(|| ApiError::new(StatusCode::NOT_FOUND, "unknown_channel", format!("no channel named {}", name)))?;
+    let res = resolve_release(&state, &org, &repo, Some(tag), requested_max_age(&params)).await?;
+    Ok(render_release(&state, &params, res).await)
+}
+
+// serve a release from the cache, fetching and caching it on a miss
+async fn resolve_release(
+    state: &AppState,
+    org: &str,
+    repo: &str,
+    tag: Option<&str>,
+    max_age: Option<Duration>
+) -> Result<ApiResponse, ApiError> {
+    let fetch = |etag| async move {
+        state.metrics.github_call();
+        fetch_release(org, repo, tag, etag).await
+    };
+    resolve_cached(state, org, repo, resolution(tag), tag, max_age, fetch).await
+}
+
+// look up a release by how it's resolved (and its tag, for pinned lookups), only running fetch on a
+// miss. fetch is handed the etag of the expired entry, if there is one, so github can answer 304
+async fn resolve_cached<F>(
+    state: &AppState,
+    org: &str,
+    repo: &str,
+    resolved_by: Lookup,
+    tag: Option<&str>,
+    max_age: Option<Duration>,
+    fetch: impl Fn(Option<String>) -> F
+) -> Result<ApiResponse, ApiError>
+where
+    F: Future<Output = Result<Fetched, ApiError>>
+{
+    logging::record_repo(org, repo);
+    state.request_rate.record();
+    // a client can ask for fresher data than the ttl, but lookups that move with new
+    // releases are never refetched more often than MIN_LATEST_TTL_SECS
+    let max_age = max_age.map(|age| match resolved_by {
+        Lookup::Tag => age,
+        _ => age.max(state.config.min_latest_ttl)
+    });
+    let key = ReleaseKey::new(org, repo, resolved_by, tag.unwrap_or_default());
+    // the cache is only locked to look up and store, never across the github call
+    let lookup = || async {
+        let mut cache = state.releases.lock().await;
+        let now = Instant::now();
+        let fresh = |entry: &CachedRelease| entry.expires_at > now
+            && max_age.is_none_or(|age| now.duration_since(entry.fetched_at) <= age);
+        cache.find(|entry| entry.key == key && fresh(entry)).map(|entry| {
+            let mut response = entry.response.clone();
+            response.cache_age = Some(now.duration_since(entry.fetched_at));
+            response
+        })
+    };
+    if let Some(response) = lookup().await {
+        state.metrics.cache_hit();
+        return Ok(response);
+    }
+
+    let _in_flight = state.in_flight.acquire(format!("{:?}", key)).await;
+    // whoever held the key before us has probably just cached it
+    if let Some(response) = lookup().await {
+        state.metrics.cache_hit();
+        return Ok(response);
+    }
+    state.metrics.cache_miss();
+    let ttl = state.release_ttl(resolved_by);
+    let etag = cached_etag(&mut *state.releases.lock().await, &key);
+    let mut fetched = fetch(etag).await?;
+    if let Fetched::NotModified = fetched {
+        if let Some(response) = revalidate(&mut *state.releases.lock().await, &key, ttl) {
+            return Ok(response);
+        }
+        // evicted while we were asking, so fetch it in full
+        fetched = fetch(None).await?;
+    }
+    let response = match fetched {
+        Fetched::Modified(response) => *response,
+        Fetched::NotModified => return Err(ApiError::new(StatusCode::BAD_GATEWAY, "upstream_error", "github sent 304 for an unconditional request"))
+    };
+    // actually put in cache, replacing the expired entry if there was one
+    store_release(&mut *state.releases.lock().await, response.clone(), ttl);
+    Ok(response)
+}
+
+// the etag github sent with a cached entry, fresh or not
+pub fn cached_etag(cache: &mut ReleaseCache, key: &ReleaseKey) -> Option<String> {
+    cache.find(|entry| entry.key == *key).and_then(|entry| entry.response.etag.clone())
+}
+
+// github says the cached entry is still current, so keep it for another ttl instead of rebuilding it
+pub fn revalidate(cache: &mut ReleaseCache, key: &ReleaseKey, ttl: Duration) -> Option<ApiResponse> {
+    let entry = cache.find(|entry| entry.key == *key)?;
+    let now = Instant::now();
+    entry.expires_at = now + ttl;
+    entry.fetched_at = now;
+    Some(entry.response.clone())
+}
+
+// if the 'tag' param is nothing or the literal "latest" then we want the latest release
+fn requested_tag(params: &HashMap<String,String>) -> Option<&str> {
+    params.get("tag").map(|t| t.as_str()).filter(|t| *t != "latest")
+}
+
+// ?max_age=<secs>, the oldest cached entry the client will accept
+fn requested_max_age(params: &HashMap<String,String>) -> Option<Duration> {
+    params.get("max_age").and_then(|age| age.parse().ok()).map(Duration::from_secs)
+}
+
+fn resolution(tag: Option<&str>) -> Lookup {
+    match tag {
+        Some(_) => Lookup::Tag,
+        None => Lookup::Latest
+    }
+}
+
+// the outcome of a release fetch that may have sent the cached entry's etag
+pub enum Fetched {
+    Modified(Box<ApiResponse>),
+    // github's 304, the cached entry is still current
+    NotModified,
+}
+
+// fetch a release straight from github, no tag means the latest release. with an etag github
+// answers 304 when the release hasn't changed, which doesn't count against the rate limit
+pub async fn fetch_release(org: &str, repo: &str, tag: Option<&str>, etag: Option<String>) -> Result<Fetched, ApiError> {
+    Ok(match fetch_github_release(org, repo, tag, etag.as_deref()).await? {
+        Some((release, etag)) => {
+            let mut response = ApiResponse::from_release(org, repo, resolution(tag), release);
+            response.etag = etag;
+            Fetched::Modified(Box::new(response))
+        },
+        None => Fetched::NotModified
+    })
+}
+
+// the release and its etag, or None when an etag was sent and github answered 304. the typed
+// releases() helpers don't expose response headers, so this goes through octocrab's raw get
+async fn fetch_github_release(org: &str, repo: &str, tag: Option<&str>, etag: Option<&str>) -> Result<Option<(Release, Option<String>)>, ApiError> {
+    let route = match tag {
+        Some(tag) => format!("/repos/{}/{}/releases/tags/{}", org, repo, utf8_percent_encode(tag, TAG_PATH)),
+        None => format!("/repos/{}/{}/releases/latest", org, repo)
+    };
+    let mut headers = HeaderMap::new();
+    if let Some(etag) = etag.and_then(|etag| HeaderValue::from_str(etag).ok()) {
+        headers.insert(IF_NONE_MATCH, etag);
+    }
+    let github = octocrab::instance();
+    let response = github._get_with_headers(route, Some(headers)).await.map_err(ApiError::from_github)?;
+    if etag.is_some() && response.status() == StatusCode::NOT_MODIFIED {
+        return Ok(None);
+    }
+    let etag = response.headers().get(ETAG).and_then(|v| v.to_str().ok()).map(str::to_string);
+    // octocrab's error drops the response, so how long github wants us to wait is read off it first
+    let retry_after = error::github_retry_after(response.headers());
+    let response = octocrab::map_github_error(response).await
+        .map_err(|e| ApiError::from_github(e).with_retry_after(retry_after))?;
+    let release = Release::from_response(response).await.map_err(ApiError::from_github)?;
+    Ok(Some((release, etag)))
+}
+
+// pages of 100 releases fetch_newest reads before giving up
+const NEWEST_MAX_PAGES: usize = 5;
+
+// newest release passing include. github lists releases newest first, so pages are read until one
+// has a match
+async fn fetch_newest(
+    state: &AppState,
+    org: &str,
+    repo: &str,
+    resolved_by: Lookup,
+    include: impl Fn(&Release) -> bool
+) -> Result<Fetched, ApiError> {
+    let github = octocrab::instance();
+    state.metrics.github_call();
+    let mut page = github.repos(org, repo).releases().list()
+        .per_page(100u8)
+        .send()
+        .await
+        .map_err(ApiError::from_github)?;
+    for scanned in 1.. {
+        if let Some(release) = page.items.iter().filter(|r| include(r)).max_by_key(|r| r.published_at.or(r.created_at)) {
+            return Ok(Fetched::Modified(Box::new(ApiResponse::from_release(org, repo, resolved_by, release.clone()))));
+        }
+        if page.next.is_none() {
+            break;
+        }
+        // a repo can have thousands of releases, so past the last page we look at nothing is
+        // known either way and it isn't answered as a 404
+        if scanned == NEWEST_MAX_PAGES {
+            return Err(ApiError::new(StatusCode::UNPROCESSABLE_ENTITY, "search_limit",
+                format!("no matching release among the newest {} releases", NEWEST_MAX_PAGES * 100)));
+        }
+        state.metrics.github_call();
+        page = match github.get_page::<Release>(&page.next).await.map_err(ApiError::from_github)? {
+            Some(next) => next,
+            None => break
+        };
+    }
+    Err(match resolved_by {
+        Lookup::Prerelease => ApiError::new(StatusCode::NOT_FOUND, "not_found", "repo has no prereleases"),
+        _ => ApiError::new(StatusCode::NOT_FOUND, "not_found", "release not found")
+    })
+}
+
+// admin only options need the ADMIN_TOKEN as a bearer token, and are disabled when it isn't set
+fn is_admin(config: &Config, headers: &HeaderMap) -> bool {
+    let token = headers.get(AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+    match (&config.admin_token, token) {
+        // constant time so the token can't be guessed a byte at a time from response timings
+        (Some(expected), Some(token)) => bool::from(expected.as_bytes().ct_eq(token.as_bytes())),
+        _ => false
+    }
+}
+
+impl ApiResponse {
+    // the last step before a release is served, by every endpoint that returns one: the
+    // DEFAULT_AVATAR_URL fallback and item ids
+    fn finalize(&mut self, config: &Config) {
+        if let (Some(author), Some(fallback)) = (self.author.as_mut(), &config.default_avatar_url) {
+            if author.image.trim().is_empty() {
+                author.image = fallback.clone();
+            }
+        }
+        Item::assign_ids(&mut self.items, "");
+    }
+
+    fn from_release(org: &str, repo: &str, resolved_by: Lookup, release: Release) -> Self {
+        let links = RepoLinks::from_release(&release);
+        ApiResponse {
+            repo: repo.to_string(),
+            org: org.to_string(),
+            latest: matches!(resolved_by, Lookup::Latest | Lookup::LatestAny),
+            resolved_by,
+            prerelease: release.prerelease,
+            draft: release.draft,
+            cache_age: None,
+            etag: None,
+            untitled: release.name.as_deref().is_none_or(|name| name.trim().is_empty()),
+            title: release.name.filter(|name| !name.trim().is_empty()).unwrap_or(release.tag_name.clone()),
+            author: release.author.map(|a| AuthorInfo {
+                name: a.login,
+                image: a.avatar_url.to_string(),
+                url: Some(a.html_url.to_string()),
+            }),
+            tag: release.tag_name,
+            published_at: release.published_at.map(|t| t.to_rfc3339()),
+            body_hash: sha256_hex(release.body.as_deref().unwrap_or_default()),
+            body: release.body.clone().unwrap_or_default(),
+            lead: release.body.as_deref().map(Item::lead_text).unwrap_or_default(),
+            footnotes: release.body.as_deref().map(Footnote::from_body).unwrap_or_default(),
+            items: Item::from_list(release.body, &links),
+            assets: release.assets.into_iter().map(|a| AssetInfo {
+                name: a.name,
+                browser_download_url: a.browser_download_url.to_string(),
+                size: a.size,
+                download_count: a.download_count,
+            }).collect(),
+            url: release.html_url.to_string(),
+            toc: None,
+            summary: None,
+            breaking: None,
+            highlights: None,
+            requirements: None,
+            milestone: None,
+            labels: None,
+            tag_message: None,
+            homepage: None,
+            default_branch: None,
+            stars: None,
+            raw_release: None,
+        }
+    }
+}
+
+// put a release in the cache, overwriting any entry for the same lookup so refreshes don't leave stale duplicates behind
+pub fn store_release(cache: &mut ReleaseCache, response: ApiResponse, ttl: Duration) {
+    let key = ReleaseKey::of(&response);
+    let existing = cache.find(|entry| entry.key == key);
+    let now = Instant::now();
+    let entry = CachedRelease {
+        key,
+        response,
+        expires_at: now + ttl,
+        fetched_at: now,
+    };
+    match existing {
+        Some(existing) => **existing = entry,
+        None => {
+            cache.insert(Box::new(entry));
+        }
+    }
+}
+
+// true when a query param is present and set to the literal "true"
+fn flag(params: &HashMap<String,String>, name: &str) -> bool {
+    params.get(name).is_some_and(|v| v == "true")
+}
+
+// repo metadata is best effort, a failed lookup just leaves the fields off the response
+async fn get_repo_meta(state: &AppState, org: &str, repo: &str) -> Option<RepoMeta> {
+    {
+        let mut cache = state.repo_meta.lock().await;
+        let ttl = state.config.repo_meta_ttl;
+        if let Some(meta) = cache.find(|m| m.org == org && m.repo == repo && m.fetched_at.elapsed() < ttl) {
+            return Some(meta.clone());
+        }
+    }
+
+    state.metrics.github_call();
+    let repository = octocrab::instance().repos(org, repo).get().await.map_err(|e| {
+        tracing::warn!("failed to fetch repo metadata: {}", e);
+    }).ok()?;
+    let meta = RepoMeta {
+        org: org.to_string(),
+        repo: repo.to_string(),
+        homepage: repository.homepage.filter(|h| !h.is_empty()),
+        default_branch: repository.default_branch,
+        stars: repository.stargazers_count.map(u64::from),
+        fetched_at: Instant::now(),
+    };
+
+    let mut cache = state.repo_meta.lock().await;
+    match cache.find(|m| m.org == org && m.repo == repo) {
+        Some(existing) => *existing = meta.clone(), // overwrite the expired entry
+        None => {
+            cache.insert(meta.clone());
+        }
+    }
+    Some(meta)
+}
+
+// milestone and labels of the pull requests linked from a release's notes (up to META_MAX_PULLS of
+// them): the first milestone found wins, labels are merged in the order they're first seen.
+// best effort like repo metadata, pulls that fail to load are skipped
+async fn get_release_meta(state: &AppState, res: &ApiResponse) -> ReleaseMeta {
+    let (org, repo, tag) = (res.org.as_str(), res.repo.as_str(), res.tag.as_str());
+    {
+        let mut cache = state.release_meta.lock().await;
+        let ttl = state.config.repo_meta_ttl;
+        if let Some(meta) = cache.find(|m| m.org == org && m.repo == repo && m.tag == tag && m.fetched_at.elapsed() < ttl) {
+            return meta.clone();
+        }
+    }
+
+    let mut meta = ReleaseMeta {
+        org: org.to_string(),
+        repo: repo.to_string(),
+        tag: tag.to_string(),
+        milestone: None,
+        labels: vec![],
+        fetched_at: Instant::now(),
+    };
+    let repo_url = res.url.split_once("/releases/").map_or(res.url.as_str(), |(repo, _)| repo);
+    for number in linked_pulls(&res.items, repo_url).into_iter().take(state.config.meta_max_pulls) {
+        state.metrics.github_call();
+        let issue = match octocrab::instance().issues(org, repo).get(number).await {
+            Ok(issue) => issue,
+            Err(e) => {
+                tracing::warn!("failed to fetch pull #{}: {}", number, e);
+                continue;
             }
         };
-        result
+        if meta.milestone.is_none() {
+            meta.milestone = issue.milestone.map(|m| m.title);
+        }
+        for label in issue.labels {
+            if !meta.labels.contains(&label.name) {
+                meta.labels.push(label.name);
+            }
+        }
+    }
+
+    let mut cache = state.release_meta.lock().await;
+    match cache.find(|m| m.org == org && m.repo == repo && m.tag == tag) {
+        Some(existing) => *existing = meta.clone(),
+        None => {
+            cache.insert(meta.clone());
+        }
+    }
+    meta
+}
+
+// characters that would end the path early if left in a tag name, slashes are kept since
+// github expects them raw in ref paths
+const TAG_PATH: &AsciiSet = &CONTROLS.add(b' ').add(b'#').add(b'?').add(b'%');
+
+// message of an annotated tag: the ref points at a tag object for annotated tags and straight at
+// the commit for lightweight ones, which have no message. failures are logged and give None
+async fn get_tag_message(state: &AppState, org: &str, repo: &str, tag: &str) -> Option<String> {
+    {
+        let mut cache = state.tag_messages.lock().await;
+        let ttl = state.config.repo_meta_ttl;
+        if let Some(entry) = cache.find(|t| t.org == org && t.repo == repo && t.tag == tag && t.fetched_at.elapsed() < ttl) {
+            return entry.message.clone();
+        }
+    }
+
+    let github = octocrab::instance();
+    let route = format!("/repos/{}/{}/git/ref/tags/{}", org, repo, utf8_percent_encode(tag, TAG_PATH));
+    state.metrics.github_call();
+    let message = match github.get::<serde_json::Value, _, ()>(route, None).await {
+        Ok(reference) if reference["object"]["type"] == "tag" => {
+            let sha = reference["object"]["sha"].as_str().unwrap_or_default();
+            let route = format!("/repos/{}/{}/git/tags/{}", org, repo, sha);
+            state.metrics.github_call();
+            match github.get::<serde_json::Value, _, ()>(route, None).await {
+                Ok(tag) => tag["message"].as_str().map(|m| m.trim().to_string()).filter(|m| !m.is_empty()),
+                Err(e) => {
+                    tracing::warn!("failed to fetch tag object: {}", e);
+                    return None;
+                }
+            }
+        },
+        Ok(_) => None,
+        Err(e) => {
+            tracing::warn!("failed to fetch tag ref: {}", e);
+            return None;
+        }
     };
 
-    match release {
-        Ok(res) => Ok(Json(res)),
-        Err(e) => Err(e)
+    let entry = TagMessage {
+        org: org.to_string(),
+        repo: repo.to_string(),
+        tag: tag.to_string(),
+        message: message.clone(),
+        fetched_at: Instant::now(),
+    };
+    let mut cache = state.tag_messages.lock().await;
+    match cache.find(|t| t.org == org && t.repo == repo && t.tag == tag) {
+        Some(existing) => *existing = entry,
+        None => {
+            cache.insert(entry);
+        }
     }
+    message
 }
 
-#[derive(Serialize, Debug, Clone)]
+// numbers of the pull requests (or issues, which carry milestones/labels too) the notes point at,
+// as full urls into this repo or `#123` references, in order without repeats
+fn linked_pulls(items: &[Item], repo_url: &str) -> Vec<u64> {
+    let prefixes = [format!("{}/pull/", repo_url), format!("{}/issues/", repo_url), "#".to_string()];
+    let mut pulls = vec![];
+    for item in items {
+        for word in strip_tags(&item.text).split_whitespace() {
+            let number = prefixes.iter()
+                .find_map(|prefix| word.trim_start_matches('(').strip_prefix(prefix.as_str()))
+                .and_then(|rest| rest.split(|c: char| !c.is_ascii_digit()).next())
+                .and_then(|n| n.parse::<u64>().ok());
+            if let Some(number) = number.filter(|n| !pulls.contains(n)) {
+                pulls.push(number);
+            }
+        }
+        if let Some(children) = &item.items {
+            for number in linked_pulls(children, repo_url) {
+                if !pulls.contains(&number) {
+                    pulls.push(number);
+                }
+            }
+        }
+    }
+    pulls
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct AuthorInfo {
     pub name: String,
     pub image: String,
+    // github profile page
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
 }
 
-#[derive(Serialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AssetInfo {
+    pub name: String,
+    pub browser_download_url: String,
+    pub size: i64,
+    pub download_count: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Item {
+    // stable key for list diffing: a hash of the item's position, category and text
+    // (nested items also hash their group's id), filled in on every response
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub id: String,
     pub category: String,
+    // html: the notes' own text is escaped and their formatting becomes tags (<b>, <a href> etc.).
+    // "image" items have their url here instead
+    pub text: String,
+    // github style slug for headings, so clients can link to sections
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub anchor: Option<String>,
+    // contents of a collapsible "details" group, with the summary as the group's text
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub items: Option<Vec<Item>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+    // alt text of "image" items
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alt: Option<String>,
+    // how deeply the item is nested in bullet lists, 1 for a top level bullet, unset outside lists
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub depth: Option<u8>,
+}
+
+// a footnote definition, referenced from item text by its `[^label]` marker
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Footnote {
+    pub label: String,
+    pub text: String,
+}
+
+impl Footnote {
+    fn from_body(body: &str) -> Vec<Self> {
+        match markdown::to_mdast(body, &ParseOptions::gfm()) {
+            Ok(Node::Root(root)) => root.children.iter().filter_map(|node| match node {
+                Node::FootnoteDefinition(definition) => Some(Footnote {
+                    label: definition.label.clone().unwrap_or(definition.identifier.clone()),
+                    text: normalize_whitespace(&node.to_string()),
+                }),
+                _ => None
+            }).collect(),
+            _ => vec![]
+        }
+    }
+
+    fn marker(&self) -> String {
+        format!("[^{}]", self.label)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TocEntry {
+    pub level: u8,
+    // plain text, unlike the heading item's html
     pub text: String,
+    pub anchor: String,
 }
 
 impl Item {
-    fn from_list(body: Option<String>) -> Vec<Self> {
+    fn new(category: impl Into<String>, text: impl Into<String>) -> Self {
+        Item {
+            id: String::new(),
+            category: category.into(),
+            text: text.into(),
+            anchor: None,
+            items: None,
+            url: None,
+            image: None,
+            alt: None,
+            depth: None,
+        }
+    }
+
+    fn assign_ids(items: &mut [Item], parent: &str) {
+        for (index, item) in items.iter_mut().enumerate() {
+            let key = format!("{}\0{}\0{}\0{}", parent, index, item.category, item.text);
+            item.id = sha256_hex(&key)[..16].to_string();
+            if let Some(children) = item.items.as_mut() {
+                Item::assign_ids(children, &item.id);
+            }
+        }
+    }
+
+    // heading level for "heading-N" items
+    fn heading_level(&self) -> Option<u8> {
+        self.category.strip_prefix("heading-").and_then(|level| level.parse().ok())
+    }
+
+    fn from_list(body: Option<String>, links: &RepoLinks) -> Vec<Self> {
         match body {
             None => vec![],
             Some(notes) => {
                 let ast = markdown::to_mdast(notes.as_str(), &ParseOptions::gfm());
                 match ast {
-                    Ok(node) => {
-                        match Self::build_items(&node, None) {
-                            Some(items) => Self::reduce_ast(items),
+                    Ok(mut node) => {
+                        links.absolutize(&mut node);
+                        match Self::build_items(&node, "text") {
+                            Some(items) => Self::assign_anchors(Self::reduce_items(items)),
                             _ => vec![]
                         }
                     },
+                    // keep the notes readable as plain text rather than dropping them
                     Err(e) => {
-                        vec![]
+                        tracing::warn!("failed to parse release notes: {}", e);
+                        vec![Item::new("text", escape_html(&notes))]
                     }
                 }
             }
         }
     }
 
+    // reduce_ast merges across blocks so it can't be isolated per child like build_items, if it
+    // fails the items are served unmerged instead
+    fn reduce_items(items: Vec<Item>) -> Vec<Item> {
+        match panic::catch_unwind(AssertUnwindSafe(|| Self::reduce_ast(items.clone()))) {
+            Ok(reduced) => reduced,
+            Err(_) => {
+                tracing::warn!("failed to reduce items, serving them unmerged");
+                items.into_iter().filter(|item| !item.category.starts_with("break")).collect()
+            }
+        }
+    }
+
     fn reduce_ast(items: Vec<Item>) -> Vec<Item> {
         let mut item_queue = VecDeque::from(items);
         let mut transformed = vec![];
+        // open <details> groups, each with the items collected outside it
+        let mut groups: Vec<(Item, Vec<Item>)> = vec![];
         let mut building = String::new();
         let mut building_type = "text";
+        let mut building_depth = None;
         while let Some(next) = item_queue.pop_front() {
-            if building.len() > 0 
-                && (next.category.starts_with("break") || ((building_type == "bold" || building_type == "italic")
-                && building_type != next.category && !next.category.starts_with("http"))) {
-                transformed.push(Item {
-                    category: building_type.to_string(),
-                    text: building.clone()
-                });
-                building_type = "text";
-                building.clear();
+            // headings, images, tasks and quotes are their own item, never part of the running text
+            if next.heading_level().is_some() || matches!(next.category.as_str(), "image" | "task-done" | "task-todo" | "quote")
+                || next.category.starts_with("callout-") {
+                Self::flush(&mut transformed, &mut building, &mut building_type, building_depth);
+                transformed.push(next);
                 continue;
             }
             match next.category.as_str() {
-                "italic" => {
-                    building_type = "italic";
-                    building.push_str(next.text.as_str());
-                },
-                "bold" => {
-                    building_type = "bold";
-                    building.push_str(next.text.as_str());
+                "details" => {
+                    Self::flush(&mut transformed, &mut building, &mut building_type, building_depth);
+                    groups.push((next, std::mem::take(&mut transformed)));
+                    continue;
                 },
-                _ => {
-                    if next.text != "" {
-                        building.push_str(next.text.as_str());
+                "details-end" => {
+                    Self::flush(&mut transformed, &mut building, &mut building_type, building_depth);
+                    if let Some((mut group, outer)) = groups.pop() {
+                        group.items = Some(std::mem::replace(&mut transformed, outer));
+                        transformed.push(group);
                     }
+                    continue;
+                },
+                _ => {}
+            }
+            // the outermost bold/italic is the item's category, any styling nested inside it
+            // (more emphasis, code, links) becomes tags, e.g. "bold-italic" is bold "<i>text</i>"
+            let depth = next.depth;
+            let (category, text) = match next.category.split_once('-') {
+                _ if next.category.starts_with("break") => ("break", String::new()),
+                Some(("bold", inner)) => ("bold", Item { category: inner.to_string(), ..next }.inline_html()),
+                Some(("italic", inner)) => ("italic", Item { category: inner.to_string(), ..next }.inline_html()),
+                _ => match next.category.as_str() {
+                    "bold" => ("bold", next.text),
+                    "italic" => ("italic", next.text),
+                    _ => ("text", next.inline_html())
                 }
+            };
+            // a sub-bullet never joins its parent's text, even without a break between them
+            if category == "break" || building_type != category || (!building.is_empty() && building_depth != depth) {
+                Self::flush(&mut transformed, &mut building, &mut building_type, building_depth);
             }
+            if !text.is_empty() {
+                building_type = category;
+                building_depth = depth;
+                building.push_str(text.as_str());
+            }
+        }
+        Self::flush(&mut transformed, &mut building, &mut building_type, building_depth);
+        // close any <details> left open at the end of the notes
+        while let Some((mut group, outer)) = groups.pop() {
+            group.items = Some(std::mem::replace(&mut transformed, outer));
+            transformed.push(group);
         }
         transformed
     }
 
-    fn build_items(node: &Node, context: Option<&Node>) -> Option<Vec<Self>> {
+    // emit the text built up so far as an item and start a new run
+    fn flush(transformed: &mut Vec<Item>, building: &mut String, building_type: &mut &str, depth: Option<u8>) {
+        if !building.is_empty() {
+            let mut item = Item::new(*building_type, std::mem::take(building));
+            item.depth = depth;
+            transformed.push(item);
+        }
+        *building_type = "text";
+    }
+
+    // <details>/<summary> arrive as raw html, usually split over several nodes with markdown
+    // in between, so just mark where each group opens and closes and let reduce_ast nest them
+    fn html_items(html: &str) -> Option<Vec<Self>> {
+        let lower = html.to_ascii_lowercase();
+        let open = lower.find("<details");
+        let close = lower.find("</details>");
+        if open.is_none() && close.is_none() {
+            return None;
+        }
+
+        let mut items = vec![];
+        let mut body_start = 0;
+        if let Some(open) = open {
+            let tag_end = lower[open..].find('>').map_or(lower.len(), |i| open + i + 1);
+            body_start = tag_end;
+            let mut label = String::new();
+            if let Some(summary) = lower[tag_end..].find("<summary").map(|i| tag_end + i) {
+                let content_start = lower[summary..].find('>').map_or(lower.len(), |i| summary + i + 1);
+                let content_end = lower[content_start..].find("</summary>").map_or(lower.len(), |i| content_start + i);
+                label = strip_tags(&html[content_start..content_end]);
+                body_start = (content_end + "</summary>".len()).min(html.len());
+            }
+            items.push(Item::new("details", label.trim()));
+        }
+        // content sharing the html block with the tags, e.g. <details><summary>a</summary>b</details>
+        let body_end = close.filter(|c| *c >= body_start).unwrap_or(html.len());
+        let body = strip_tags(&html[body_start..body_end]);
+        if !body.trim().is_empty() {
+            items.push(Item::new("text", body.trim()));
+            items.push(Item::new("break-p", ""));
+        }
+        if close.is_some() {
+            items.push(Item::new("details-end", ""));
+        }
+        Some(items)
+    }
+
+    // style is the formatting the node sits in, outermost first, e.g. "bold-italic" for
+    // **_text_**, and "text" when there is none
+    fn build_items(node: &Node, style: &str) -> Option<Vec<Self>> {
+        let children = |children: &[Node], style: &str| -> Vec<Self> {
+            children.iter().filter_map(|i| Self::build_child(i, style)).flatten().collect()
+        };
         match node {
-            Node::Root(root) => {
-                Some(root.children.iter().filter_map(|i| Self::build_items(i, Some(node))).flat_map(|i|i).collect())
-            },
+            Node::Root(root) => Some(children(&root.children, style)),
             Node::Paragraph(paragraph) => {
-                let break_item = Item {
-                    category: "break-p".to_string(),
-                    text: "".to_string()
-                };
-                if paragraph.children.len() == 1 && paragraph.children.first().is_some_and(|n| n.type_id() == (&Node::Image).type_id()) {
-                    None
-                } else {
-                    Some(paragraph.children.iter().filter_map(|i| Self::build_items(i, Some(node))).flat_map(|i|i).chain([break_item]).collect())
-                }
+                let break_item = Item::new("break-p", "");
+                Some(children(&paragraph.children, style).into_iter().chain([break_item]).collect())
+            },
+            Node::Heading(heading) => {
+                Some(vec![Item::new(format!("heading-{}", heading.depth), escape_html(&node.to_string()))])
             },
+            Node::Html(html) => Self::html_items(&html.value),
+            // every list a bullet sits in adds one to its depth, so a sub-bullet's items come out
+            // one deeper than its parent's
             Node::List(list) => {
-                let break_item = Item {
-                    category: "break-l".to_string(),
-                    text: "".to_string()
-                };
-                Some(list.children.iter().filter_map(|i| Self::build_items(i, Some(node))).flat_map(|i|i).chain([break_item]).collect())
+                let mut items = children(&list.children, style);
+                for item in items.iter_mut() {
+                    item.depth = Some(item.depth.map_or(1, |depth| depth.saturating_add(1)));
+                }
+                items.push(Item::new("break-l", ""));
+                Some(items)
             },
-            Node::ListItem(item) => {
-                Some(item.children.iter().filter_map(|i| Self::build_items(i, Some(node))).flat_map(|i|i).collect())
+            // checklist entries keep their state with their first paragraph rendered inline like link
+            // text, anything after it (nested lists, more paragraphs) is built as usual
+            Node::ListItem(item) => match item.checked {
+                Some(checked) => {
+                    let (lead, rest) = match item.children.split_first() {
+                        Some((Node::Paragraph(lead), rest)) => (&lead.children[..], rest),
+                        _ => (&[][..], &item.children[..])
+                    };
+                    let text: String = children(lead, style).iter()
+                        .filter(|i| !i.category.starts_with("break"))
+                        .map(Item::inline_html)
+                        .collect();
+                    let category = if checked { "task-done" } else { "task-todo" };
+                    let mut items = vec![Item::new(category, text.trim())];
+                    items.extend(children(rest, style));
+                    Some(items)
+                },
+                None => Some(children(&item.children, style))
             },
-            Node::Strong(strong) => {
-                Some(strong.children.iter().filter_map(|i| Self::build_items(i, Some(node))).flat_map(|i|i).collect())
+            Node::Strong(strong) => Some(children(&strong.children, &Self::nested_style(style, "bold"))),
+            Node::Emphasis(italic) => Some(children(&italic.children, &Self::nested_style(style, "italic"))),
+            Node::Delete(strike) => Some(children(&strike.children, &Self::nested_style(style, "strike"))),
+            // a quote is one item with its paragraphs on separate lines, github style callouts
+            // (`> [!WARNING]`) are "callout-warning" etc. with the marker dropped
+            Node::BlockQuote(quote) => {
+                let mut text = String::new();
+                for item in children(&quote.children, style) {
+                    match item.category.starts_with("break") {
+                        true if !text.ends_with('\n') => text.push('\n'),
+                        true => {},
+                        false => text.push_str(&item.inline_html())
+                    }
+                }
+                let (category, text) = match Self::callout(text.trim()) {
+                    Some((kind, rest)) => (format!("callout-{}", kind), rest),
+                    None => ("quote".to_string(), text.trim())
+                };
+                Some(vec![Item::new(category, text.trim())])
             },
+            // the link text is rendered up front (keeping any formatting inside it as tags) so the
+            // reducer only has to wrap it in an anchor
             Node::Link(link) => {
-                Some(link.children.iter().filter_map(|i| Self::build_items(i, Some(node))).flat_map(|i|i).collect())
-            },
-            Node::Emphasis(italic) => {
-                Some(italic.children.iter().filter_map(|i| Self::build_items(i, Some(node))).flat_map(|i|i).collect())
+                let text: String = children(&link.children, "text").iter().map(Item::inline_html).collect();
+                let mut item = Item::new(Self::nested_style(style, "link"), text);
+                item.url = Some(link.url.clone());
+                Some(vec![item])
             },
             Node::InlineCode(code) => {
-                Some(vec![Item {
-                    category: "code".to_string(),
-                    text: code.value.clone()
-                }])
+                // keep the surrounding styling so linked/emphasised code doesn't lose it
+                Some(vec![Item::new(Self::nested_style(style, "code"), escape_html(&code.value))])
             }
             Node::Text(text) => {
-                let text_type = match context {
-                    Some(Node::Strong(_)) => "bold",
-                    Some(Node::Emphasis(_)) => "italic",
-                    Some(Node::Link(link)) => link.url.as_str(),
-                    _ => "text"
-                };
-                Some(vec![Item {
-                    category: text_type.to_string(),
-                    text: text.value.clone(),
-                }])
+                Some(vec![Item::new(style, escape_html(&text.value))])
+            },
+            // images stand alone whatever styling they're in, the url as text so clients that only
+            // look at text still get something
+            Node::Image(image) => {
+                let mut item = Item::new("image", image.url.clone());
+                item.url = Some(image.url.clone());
+                item.alt = Some(image.alt.clone()).filter(|alt| !alt.is_empty());
+                Some(vec![item])
             },
+            // the definitions themselves go in the response's footnotes list
+            Node::FootnoteReference(reference) => {
+                let label = reference.label.clone().unwrap_or(reference.identifier.clone());
+                Some(vec![Item::new(style, format!("[^{}]", label))])
+            },
+            _ => None
+        }
+    }
+
+    // the kind of a github callout and the text after its marker, e.g. ("warning", "be careful")
+    // for "[!WARNING] be careful"
+    fn callout(text: &str) -> Option<(String, &str)> {
+        let rest = text.strip_prefix("[!")?;
+        let (kind, rest) = rest.split_once(']')?;
+        let kind = kind.to_lowercase();
+        match kind.as_str() {
+            "note" | "tip" | "important" | "warning" | "caution" => Some((kind, rest)),
             _ => None
         }
     }
 
+    fn nested_style(outer: &str, inner: &str) -> String {
+        match outer {
+            "text" => inner.to_string(),
+            // **bold inside __bold__** is still just bold
+            outer if outer.split('-').any(|s| s == inner) => outer.to_string(),
+            outer => format!("{}-{}", outer, inner)
+        }
+    }
+
+    // one child node, isolated so a construct that trips up build_items only costs its own subtree
+    // (rendered as its plain text, in the surrounding style) instead of the whole release. the
+    // innermost child around the failure is the one that falls back
+    fn build_child(node: &Node, style: &str) -> Option<Vec<Self>> {
+        Self::isolated(node, style, || Self::build_items(node, style))
+    }
+
+    // the items build returns, or node as plain text if it panics
+    fn isolated(node: &Node, style: &str, build: impl FnOnce() -> Option<Vec<Self>>) -> Option<Vec<Self>> {
+        match panic::catch_unwind(AssertUnwindSafe(build)) {
+            Ok(items) => items,
+            Err(_) => {
+                tracing::warn!(subtree = ?node, "failed to build items, falling back to plain text");
+                let mut items = vec![Item::new(style, escape_html(&node.to_string()))];
+                if matches!(node, Node::Paragraph(_) | Node::Heading(_) | Node::List(_) | Node::ListItem(_)
+                    | Node::BlockQuote(_) | Node::Code(_) | Node::Table(_) | Node::Html(_)) {
+                    items.push(Item::new("break-p", ""));
+                }
+                Some(items)
+            }
+        }
+    }
+
+    // plain text of the first non-empty paragraph, headings are skipped
+    fn lead_text(body: &str) -> String {
+        let items = match markdown::to_mdast(body, &ParseOptions::gfm()) {
+            Ok(node) => Self::build_items(&node, "text").unwrap_or_default(),
+            Err(_) => vec![]
+        };
+        let mut lead = String::new();
+        for item in items {
+            if item.category.starts_with("break") {
+                if !lead.trim().is_empty() {
+                    break;
+                }
+                lead.clear();
+            } else if item.heading_level().is_none() && !item.category.starts_with("details") && item.category != "image" {
+                lead.push_str(&plain_text(&item.text));
+            }
+        }
+        normalize_whitespace(&lead)
+    }
+
+    // slug each heading the way github does, numbering repeats so anchors stay unique
+    fn assign_anchors(mut items: Vec<Item>) -> Vec<Item> {
+        let mut seen: HashMap<String, usize> = HashMap::new();
+        for item in items.iter_mut().filter(|i| i.heading_level().is_some()) {
+            let slug = slugify(&plain_text(&item.text));
+            let count = seen.entry(slug.clone()).or_insert(0);
+            item.anchor = Some(match *count {
+                0 => slug,
+                n => format!("{}-{}", slug, n)
+            });
+            *count += 1;
+        }
+        items
+    }
+
+    // the items under the first heading matching one of the labels, up to the next heading
+    // at the same or a higher level (subsections are included)
+    fn section(items: &[Item], labels: &[String]) -> Vec<Item> {
+        let start = items.iter().position(|item| {
+            item.heading_level().is_some() && {
+                let text = plain_text(&item.text).to_lowercase();
+                labels.iter().any(|label| has_label(&text, label))
+            }
+        });
+        match start {
+            Some(start) => {
+                let level = items[start].heading_level().unwrap_or(1);
+                items[start + 1..].iter()
+                    .take_while(|item| item.heading_level().is_none_or(|l| l > level))
+                    .cloned()
+                    .collect()
+            },
+            None => vec![]
+        }
+    }
+
+    // lines mentioning one of the patterns (case insensitive) followed by a version, e.g. "Requires
+    // Node >= 18" but not "requires no migration", including those inside details groups
+    fn requirements(items: &[Item], patterns: &[String]) -> Vec<String> {
+        let mut found: Vec<String> = vec![];
+        for item in items {
+            for line in item.text.lines() {
+                let line = normalize_whitespace(&plain_text(line));
+                let lower = line.to_lowercase();
+                let versioned = |p: &String| lower.match_indices(p.as_str())
+                    .any(|(at, _)| lower[at + p.len()..].contains(|c: char| c.is_ascii_digit()));
+                if patterns.iter().any(versioned) && !found.contains(&line) {
+                    found.push(line);
+                }
+            }
+            if let Some(children) = &item.items {
+                for line in Self::requirements(children, patterns) {
+                    if !found.contains(&line) {
+                        found.push(line);
+                    }
+                }
+            }
+        }
+        found
+    }
+
+    fn table_of_contents(items: &[Item]) -> Vec<TocEntry> {
+        items.iter().filter_map(|item| {
+            Some(TocEntry {
+                level: item.heading_level()?,
+                text: plain_text(&item.text),
+                anchor: item.anchor.clone()?,
+            })
+        }).collect()
+    }
+
+    // the item as inline html, its style wrapped outermost first, e.g. "bold-italic" is <b><i>text</i></b>
+    fn inline_html(&self) -> String {
+        self.category.rsplit('-').fold(self.text.clone(), |html, style| match style {
+            "bold" => format!("<b>{}</b>", html),
+            "italic" => format!("<i>{}</i>", html),
+            "code" => format!("<code>{}</code>", html),
+            "strike" => format!("<s>{}</s>", html),
+            "link" => anchor(self.url.as_deref(), &html),
+            // only reached for images inside links, e.g. a linked badge
+            "image" => format!("<img src=\"{}\" alt=\"{}\">", escape_html(&html), escape_html(self.alt.as_deref().unwrap_or_default())),
+            _ => html
+        })
+    }
+}
+
+// only http(s), mailto and relative (already rewritten to the tag, or #/? only) links keep their
+// href, anything else (javascript:, data: etc.) is left as its text
+fn anchor(url: Option<&str>, html: &str) -> String {
+    match url.filter(|url| safe_href(url)) {
+        Some(url) => format!("<a href=\"{}\">{}</a>", escape_html(url), html),
+        None => html.to_string()
+    }
+}
+
+fn safe_href(url: &str) -> bool {
+    match Url::parse(url) {
+        Ok(url) => matches!(url.scheme(), "http" | "https" | "mailto"),
+        Err(e) => e == url::ParseError::RelativeUrlWithoutBase
+    }
 }
 
+// label starting at a word of text, so "breaking" matches "Breaking changes" but not
+// "Non-breaking changes" or "Unbreaking", and "breaking change" still matches its plural
+fn has_label(text: &str, label: &str) -> bool {
+    !label.is_empty() && text.match_indices(label).any(|(start, _)| {
+        !text[..start].chars().next_back().is_some_and(|c| c.is_alphanumeric() || c == '-' || c == '_')
+    })
+}
 
-#[derive(Serialize, Debug, Clone)]
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+// item text (html) back to plain text, for summaries, requirements, titles and slugs
+fn plain_text(html: &str) -> String {
+    strip_tags(html).replace("&lt;", "<").replace("&gt;", ">").replace("&quot;", "\"").replace("&amp;", "&")
+}
+
+fn strip_tags(html: &str) -> String {
+    let mut text = String::new();
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            c if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    text
+}
+
+// where repo-relative links in the notes point, the repo's tree at the release's tag
+pub struct RepoLinks {
+    // e.g. https://github.com/org/repo, taken from the release's html url so enterprise hosts work too
+    pub repo_url: String,
+    pub tag: String,
+}
+
+impl RepoLinks {
+    fn from_release(release: &Release) -> Self {
+        let html_url = release.html_url.as_str();
+        let repo_url = html_url.split_once("/releases/").map_or(html_url, |(repo, _)| repo);
+        RepoLinks {
+            repo_url: repo_url.trim_end_matches('/').to_string(),
+            tag: release.tag_name.clone(),
+        }
+    }
+
+    // rewrite relative link/image urls in place, absolute urls are left alone
+    fn absolutize(&self, node: &mut Node) {
+        match node {
+            Node::Link(link) => link.url = self.absolute_url(&link.url, "blob"),
+            // the blob page is html, images need the file itself
+            Node::Image(image) => image.url = self.absolute_url(&image.url, "raw"),
+            Node::Definition(definition) => definition.url = self.absolute_url(&definition.url, "blob"),
+            _ => {}
+        }
+        if let Some(children) = node.children_mut() {
+            for child in children {
+                self.absolutize(child);
+            }
+        }
+    }
+
+    fn absolute_url(&self, url: &str, file_kind: &str) -> String {
+        // anything with a scheme (https:, mailto:, tel:, ...) parses as a url on its own, and
+        // protocol relative, fragment and query only links already resolve against the page
+        let is_absolute = Url::parse(url).is_ok() || url.starts_with("//") || url.starts_with('#')
+            || url.starts_with('?') || url.is_empty();
+        if is_absolute {
+            return url.to_string();
+        }
+        match url.strip_prefix('/') {
+            // rooted at the host, e.g. /org/repo/pull/1
+            Some(_) => {
+                let host = self.repo_url.splitn(4, '/').take(3).collect::<Vec<_>>().join("/");
+                format!("{}{}", host, url)
+            },
+            None => {
+                let path = url.trim_start_matches("./");
+                // directories live under tree/, files under blob/ (or raw/)
+                let kind = if path.ends_with('/') { "tree" } else { file_kind };
+                format!("{}/{}/{}/{}", self.repo_url, kind, self.tag, path)
+            }
+        }
+    }
+}
+
+// cut at a word boundary when over max_len characters
+fn truncate_text(text: &str, max_len: usize) -> String {
+    if text.chars().count() <= max_len {
+        return text.to_string();
+    }
+    let cut: String = text.chars().take(max_len).collect();
+    let cut = match cut.rfind(' ') {
+        Some(space) if space > 0 => &cut[..space],
+        _ => cut.as_str()
+    };
+    format!("{}…", cut.trim_end())
+}
+
+// lowercase, drop punctuation and turn spaces into hyphens, matching github's heading anchors
+fn slugify(text: &str) -> String {
+    text.trim()
+        .to_lowercase()
+        .chars()
+        .filter_map(|c| match c {
+            ' ' => Some('-'),
+            c if c.is_alphanumeric() || c == '-' || c == '_' => Some(c),
+            _ => None
+        })
+        .collect()
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ApiResponse {
     pub repo: String,
     pub org: String,
     pub title: String,
+    // the release has no name of its own, so title is just the tag
+    #[serde(skip)]
+    pub untitled: bool,
+    // how old the cache entry was when this was served from it, None when freshly fetched
+    #[serde(skip)]
+    pub cache_age: Option<Duration>,
+    // github's etag for the release, sent back as If-None-Match when the entry expires
+    #[serde(skip)]
+    pub etag: Option<String>,
     pub latest: bool,
+    // which lookup produced this release: "latest", "latest-any" (prereleases included), "tag",
+    // "prerelease" or "list"
+    pub resolved_by: Lookup,
+    pub prerelease: bool,
+    #[serde(default)]
+    pub draft: bool,
     pub author: Option<AuthorInfo>,
     pub tag: String,
+    // rfc3339, unset for drafts
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub published_at: Option<String>,
     pub items: Vec<Item>,
+    // footnote definitions in the order they appear, empty with ?footnotes=inline
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub footnotes: Vec<Footnote>,
     pub url: String,
+    // downloadable artifacts, empty when there are none
+    #[serde(default)]
+    pub assets: Vec<AssetInfo>,
+    // sha256 (hex) of the raw release body, see ?since_hash
+    #[serde(default)]
+    pub body_hash: String,
+    // the markdown as github returned it, for ?format=markdown|html
+    #[serde(skip)]
+    pub body: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub toc: Option<Vec<TocEntry>>,
+    // plain text lead paragraph, with ?summary=true
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+    // kept with the cached entry so summaries don't need a reparse
+    #[serde(skip)]
+    pub lead: String,
+    // items from the breaking changes section, with ?breaking=true
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub breaking: Option<Vec<Item>>,
+    // items from the highlights section, with ?highlights=true
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub highlights: Option<Vec<Item>>,
+    // runtime/version requirements mentioned in the notes, with ?requirements=true
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub requirements: Option<Vec<String>>,
+    // planning info for ?meta=true, github releases have neither so they're taken from the
+    // pull requests the notes link to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub milestone: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub labels: Option<Vec<String>>,
+    // annotated tag message, with ?include_tag_message=true
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tag_message: Option<String>,
+    // only populated when requested with ?repo_meta=true
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub homepage: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_branch: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stars: Option<u64>,
+    // the release exactly as github returned it, admin only via ?raw_release=true
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub raw_release: Option<serde_json::Value>,
+}
+
+// deterministic view of a release for hashing/diffing across fetches, with ?canonical=true.
+// included: org, repo, tag, title, prerelease, author login, html url, the footnotes as (label, text),
+// and the items as (anchor, category, depth, nested items, text) with whitespace collapsed and
+// empty items dropped.
+// excluded: how it was looked up (latest, resolved_by), the avatar url, repo metadata (stars etc.),
+// and anything derived or optional (toc, summary, breaking, raw_release).
+// fields are in sorted order and absent values are omitted rather than null.
+#[derive(Serialize, Debug, Clone)]
+pub struct CanonicalRelease {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub footnotes: Vec<Footnote>,
+    pub items: Vec<CanonicalItem>,
+    pub org: String,
+    pub prerelease: bool,
+    pub repo: String,
+    pub tag: String,
+    pub title: String,
+    pub url: String,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct CanonicalItem {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub anchor: Option<String>,
+    pub category: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub depth: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub items: Option<Vec<CanonicalItem>>,
+    pub text: String,
+}
+
+impl CanonicalItem {
+    fn from_items(items: &[Item]) -> Vec<Self> {
+        items.iter()
+            .map(|item| CanonicalItem {
+                anchor: item.anchor.clone(),
+                category: item.category.clone(),
+                depth: item.depth,
+                items: item.items.as_deref().map(Self::from_items),
+                text: normalize_whitespace(&item.text),
+            })
+            .filter(|item| !item.text.is_empty() || item.items.is_some())
+            .collect()
+    }
+}
+
+fn sha256_hex(text: &str) -> String {
+    digest::digest(&digest::SHA256, text.as_bytes()).as_ref().iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+fn normalize_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+impl From<&ApiResponse> for CanonicalRelease {
+    fn from(res: &ApiResponse) -> Self {
+        CanonicalRelease {
+            author: res.author.as_ref().map(|a| a.name.clone()),
+            footnotes: res.footnotes.clone(),
+            items: CanonicalItem::from_items(&res.items),
+            org: res.org.clone(),
+            prerelease: res.prerelease,
+            repo: res.repo.clone(),
+            tag: res.tag.clone(),
+            title: normalize_whitespace(&res.title),
+            url: res.url.clone(),
+        }
+    }
+}
+
+// https://shields.io/badges/endpoint-badge
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Badge {
+    pub schema_version: u8,
+    pub label: String,
+    pub message: String,
+    pub color: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct TagMessage {
+    pub org: String,
+    pub repo: String,
+    pub tag: String,
+    pub message: Option<String>,
+    pub fetched_at: Instant,
+}
+
+#[derive(Debug, Clone)]
+pub struct ReleaseMeta {
+    pub org: String,
+    pub repo: String,
+    pub tag: String,
+    pub milestone: Option<String>,
+    pub labels: Vec<String>,
+    pub fetched_at: Instant,
+}
+
+#[derive(Debug, Clone)]
+pub struct RepoMeta {
+    pub org: String,
+    pub repo: String,
+    pub homepage: Option<String>,
+    pub default_branch: Option<String>,
+    pub stars: Option<u64>,
+    pub fetched_at: Instant,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn links() -> RepoLinks {
+        RepoLinks {
+            repo_url: "https://github.com/o/r".to_string(),
+            tag: "v1".to_string(),
+        }
+    }
+
+    fn items(body: &str) -> Vec<Item> {
+        Item::from_list(Some(body.to_string()), &links())
+    }
+
+    fn shape(items: &[Item]) -> Vec<(&str, &str)> {
+        items.iter().map(|item| (item.category.as_str(), item.text.as_str())).collect()
+    }
+
+    // the caches hold their entries inline, too much for a test thread's stack
+    fn state() -> CacheState {
+        let build = || Arc::new(AppState {
+            releases: Mutex::new(LRUCache::new()),
+            repo_meta: Mutex::new(LRUCache::new()),
+            release_meta: Mutex::new(LRUCache::new()),
+            tag_messages: Mutex::new(LRUCache::new()),
+            release_lists: Mutex::new(LRUCache::new()),
+            in_flight: InFlight::new(),
+            request_rate: RequestRate::new(),
+            rate_limit: Mutex::new(None),
+            metrics: Metrics::new(),
+            config: Config::from_env(),
+        });
+        std::thread::Builder::new().stack_size(64 << 20).spawn(build).unwrap().join().unwrap()
+    }
+
+    // the smallest release github would send, named `name` with `body` as its notes
+    fn release(tag: &str, name: Option<&str>, body: &str) -> Release {
+        serde_json::from_value(serde_json::json!({
+            "url": "https://api.github.com/repos/o/r/releases/1",
+            "html_url": format!("https://github.com/o/r/releases/tag/{}", tag),
+            "assets_url": "https://api.github.com/repos/o/r/releases/1/assets",
+            "upload_url": "https://uploads.github.com/repos/o/r/releases/1/assets",
+            "id": 1,
+            "node_id": "R_1",
+            "tag_name": tag,
+            "target_commitish": "main",
+            "name": name,
+            "body": body,
+            "draft": false,
+            "prerelease": false,
+            "assets": [],
+        })).expect("invalid test release")
+    }
+
+    #[test]
+    fn headings_sit_between_their_lists() {
+        let items = items("## Features\n\n- one\n- two\n\n## Fixes\n\n- three\n");
+        assert_eq!(shape(&items), vec![
+            ("heading-2", "Features"),
+            ("text", "one"),
+            ("text", "two"),
+            ("heading-2", "Fixes"),
+            ("text", "three"),
+        ]);
+    }
+
+    #[test]
+    fn code_keeps_link_and_emphasis() {
+        let items = items("[`code`](https://x.y) and *`it`*");
+        assert_eq!(shape(&items), vec![
+            ("text", "<a href=\"https://x.y\"><code>code</code></a> and "),
+            ("italic", "<code>it</code>"),
+        ]);
+    }
+
+    #[test]
+    fn toc_covers_every_heading_level() {
+        let items = items("# Release 1.0\n\n## Features\n\n### New API\n\ntext\n\n## Features\n\n## a < b\n");
+        let toc: Vec<(u8, String, String)> = Item::table_of_contents(&items).into_iter()
+            .map(|entry| (entry.level, entry.text, entry.anchor))
+            .collect();
+        assert_eq!(toc, vec![
+            (1, "Release 1.0".to_string(), "release-10".to_string()),
+            (2, "Features".to_string(), "features".to_string()),
+            (3, "New API".to_string(), "new-api".to_string()),
+            (2, "Features".to_string(), "features-1".to_string()),
+            (2, "a < b".to_string(), "a--b".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn details_group_holds_its_list() {
+        let items = items("before\n\n<details>\n<summary>Full changelog</summary>\n\n- one\n- two\n\n</details>\n\nafter");
+        assert_eq!(shape(&items), vec![("text", "before"), ("details", "Full changelog"), ("text", "after")]);
+        let group = items[1].items.as_deref().expect("details group has no items");
+        assert_eq!(shape(group), vec![("text", "one"), ("text", "two")]);
+    }
+
+    #[test]
+    fn relative_links_point_at_the_tag() {
+        let links = links();
+        assert_eq!(links.absolute_url("docs/guide.md", "blob"), "https://github.com/o/r/blob/v1/docs/guide.md");
+        assert_eq!(links.absolute_url("./docs/", "blob"), "https://github.com/o/r/tree/v1/docs/");
+        assert_eq!(links.absolute_url("/o/r/pull/1", "blob"), "https://github.com/o/r/pull/1");
+        assert_eq!(links.absolute_url("img/shot.png", "raw"), "https://github.com/o/r/raw/v1/img/shot.png");
+    }
+
+    #[test]
+    fn absolute_links_are_left_alone() {
+        let links = links();
+        for url in ["https://x.y/a", "mailto:a@b.c", "tel:+123", "?tab=x", "#usage", "//cdn.x.y/a", "javascript:alert(1)", "data:text/plain,hi"] {
+            assert_eq!(links.absolute_url(url, "blob"), url);
+        }
+    }
+
+    #[test]
+    fn title_prefers_name_then_heading_then_tag() {
+        let body = "# Big Release\n\n- one\n";
+        let mut named = ApiResponse::from_release("o", "r", Lookup::Tag, release("v1", Some("Named"), body));
+        title_from_body(&mut named);
+        assert_eq!(named.title, "Named");
+        assert_eq!(shape(&named.items), vec![("heading-1", "Big Release"), ("text", "one")]);
+
+        let mut blank = ApiResponse::from_release("o", "r", Lookup::Tag, release("v1", Some(" "), body));
+        title_from_body(&mut blank);
+        assert_eq!(blank.title, "Big Release");
+        assert_eq!(shape(&blank.items), vec![("text", "one")]);
+
+        let mut headless = ApiResponse::from_release("o", "r", Lookup::Tag, release("v1", None, "- one\n\n## Later\n"));
+        title_from_body(&mut headless);
+        assert_eq!(headless.title, "v1");
+        assert_eq!(shape(&headless.items), vec![("text", "one"), ("heading-2", "Later")]);
+    }
+
+    #[test]
+    fn author_item_comes_last() {
+        let mut res = ApiResponse::from_release("o", "r", Lookup::Tag, release("v1", None, "## Fixes\n\n- one\n"));
+        res.author = Some(AuthorInfo {
+            name: "octocat".to_string(),
+            image: "https://avatars.x.y/octocat".to_string(),
+            url: None,
+        });
+        append_author_item(&mut res);
+        assert_eq!(shape(&res.items), vec![("heading-2", "Fixes"), ("text", "one"), ("author", "octocat")]);
+        let author = res.items.last().unwrap();
+        assert_eq!(author.url.as_deref(), Some("https://github.com/octocat"));
+        assert_eq!(author.image.as_deref(), Some("https://avatars.x.y/octocat"));
+    }
+
+    #[test]
+    fn strip_images_drops_inline_and_block_images() {
+        let mut items = items("![logo](https://x.y/logo.png)\n\nsee ![icon](https://x.y/i.png) here\n\n- [![badge](https://x.y/b.svg)](https://x.y)\n- kept\n");
+        strip_images(&mut items);
+        assert_eq!(shape(&items), vec![("text", "see "), ("text", " here"), ("text", "kept")]);
+    }
+
+    #[test]
+    fn finalize_fills_in_a_missing_avatar() {
+        let mut config = Config::from_env();
+        config.default_avatar_url = Some("https://x.y/default.png".to_string());
+        let author = |image: &str| Some(AuthorInfo { name: "octocat".to_string(), image: image.to_string(), url: None });
+
+        let mut missing = ApiResponse::from_release("o", "r", Lookup::List, release("v1", None, "- one\n"));
+        missing.author = author(" ");
+        missing.finalize(&config);
+        assert_eq!(missing.author.unwrap().image, "https://x.y/default.png");
+        assert!(!missing.items[0].id.is_empty());
+
+        let mut present = ApiResponse::from_release("o", "r", Lookup::List, release("v1", None, "- one\n"));
+        present.author = author("https://avatars.x.y/octocat");
+        present.finalize(&config);
+        assert_eq!(present.author.unwrap().image, "https://avatars.x.y/octocat");
+    }
+
+    #[test]
+    fn a_failing_node_only_costs_its_subtree() {
+        let Ok(Node::Root(root)) = markdown::to_mdast("intro\n\nsee [the docs](https://x.y) now\n\n- one\n- two\n\nafter", &ParseOptions::gfm()) else {
+            panic!("notes didn't parse");
+        };
+        // the second paragraph stands in for a construct build_items can't handle
+        let built: Vec<Item> = root.children.iter().enumerate()
+            .filter_map(|(index, child)| Item::isolated(child, "text", || match index {
+                1 => panic!("injected failure"),
+                _ => Item::build_items(child, "text")
+            }))
+            .flatten()
+            .collect();
+        let items = Item::reduce_items(built);
+        assert_eq!(shape(&items), vec![
+            ("text", "intro"),
+            ("text", "see the docs now"),
+            ("text", "one"),
+            ("text", "two"),
+            ("text", "after"),
+        ]);
+        assert_eq!(items.iter().map(|item| item.depth).collect::<Vec<_>>(), vec![None, None, Some(1), Some(1), None]);
+    }
+
+    #[test]
+    fn link_keeps_its_formatting() {
+        let items = items("See [the **docs**](https://x.y)");
+        assert_eq!(shape(&items), vec![("text", "See <a href=\"https://x.y\">the <b>docs</b></a>")]);
+    }
+
+    #[test]
+    fn only_safe_links_keep_an_href() {
+        let items = items("[a](https://x.y/?a=1&b=2) [b](mailto:a@b.c) [c](#usage) [d](javascript:alert(1)) [e](data:text/html,hi)");
+        let expected = "<a href=\"https://x.y/?a=1&amp;b=2\">a</a> <a href=\"mailto:a@b.c\">b</a> <a href=\"#usage\">c</a> d e";
+        assert_eq!(shape(&items), vec![("text", expected)]);
+    }
+
+    #[test]
+    fn inline_code_and_nested_emphasis() {
+        assert_eq!(shape(&items("run `cargo <cmd> && go`")), vec![("text", "run <code>cargo &lt;cmd&gt; &amp;&amp; go</code>")]);
+        assert_eq!(shape(&items("**_both_**")), vec![("bold", "<i>both</i>")]);
+        assert_eq!(shape(&items("_**both**_")), vec![("italic", "<b>both</b>")]);
+    }
+
+    #[test]
+    fn text_is_escaped_before_it_is_wrapped() {
+        let notes = items("## a < b\n\n[x & \"y\"](https://x.y) <3 ![say \"hi\" <b>](https://x.y/a.png)");
+        assert_eq!(notes[0].text, "a &lt; b");
+        assert_eq!(notes[0].anchor.as_deref(), Some("a--b"));
+        assert_eq!(notes[1].text, "<a href=\"https://x.y\">x &amp; &quot;y&quot;</a> &lt;3 ");
+        assert_eq!(notes[2].alt.as_deref(), Some("say \"hi\" <b>"));
+        let linked = items("[![say \"hi\"](https://x.y/a.png?s=1&t=2)](https://x.y)");
+        assert_eq!(linked[0].text, "<a href=\"https://x.y\"><img src=\"https://x.y/a.png?s=1&amp;t=2\" alt=\"say &quot;hi&quot;\"></a>");
+    }
+
+    #[tokio::test]
+    async fn misses_for_different_repos_fetch_in_parallel() {
+        let state = state();
+        // each fetch waits for the other to start, so this only finishes if neither waits on the other's key
+        let started = tokio::sync::Barrier::new(2);
+        let fetch = |repo: &'static str| {
+            let started = &started;
+            move |_| async move {
+                started.wait().await;
+                Ok(Fetched::Modified(Box::new(ApiResponse::from_release("o", repo, Lookup::Latest, release("v1", None, "")))))
+            }
+        };
+        let both = async {
+            tokio::join!(
+                resolve_cached(&state, "o", "a", Lookup::Latest, None, None, fetch("a")),
+                resolve_cached(&state, "o", "b", Lookup::Latest, None, None, fetch("b")),
+            )
+        };
+        let (a, b) = tokio::time::timeout(Duration::from_secs(5), both).await.expect("misses for different repos were serialized");
+        assert_eq!((a.unwrap().repo, b.unwrap().repo), ("a".to_string(), "b".to_string()));
+    }
+
+    #[tokio::test]
+    async fn misses_for_the_same_release_share_one_fetch() {
+        let state = state();
+        let fetches = std::sync::atomic::AtomicUsize::new(0);
+        let fetch = |_| async {
+            fetches.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            Ok(Fetched::Modified(Box::new(ApiResponse::from_release("o", "r", Lookup::Latest, release("v1", None, "")))))
+        };
+        let (first, second) = tokio::join!(
+            resolve_cached(&state, "o", "r", Lookup::Latest, None, None, fetch),
+            resolve_cached(&state, "o", "r", Lookup::Latest, None, None, fetch),
+        );
+        assert_eq!(fetches.load(std::sync::atomic::Ordering::SeqCst), 1);
+        assert!(first.unwrap().cache_age.is_none() != second.unwrap().cache_age.is_none());
+    }
+
+    #[test]
+    fn images_are_their_own_items() {
+        let items = items("![alt](https://img.png)\n\nsee ![](https://img.png) here");
+        assert_eq!(shape(&items), vec![("image", "https://img.png"), ("text", "see "), ("image", "https://img.png"), ("text", " here")]);
+        assert_eq!((items[0].url.as_deref(), items[0].alt.as_deref()), (Some("https://img.png"), Some("alt")));
+        assert_eq!(items[2].alt, None);
+    }
+
+    #[test]
+    fn task_items_keep_their_state() {
+        let flat = items("- [x] Done `a`\n- [ ] Todo **b**\n- plain\n");
+        assert_eq!(shape(&flat), vec![("task-done", "Done <code>a</code>"), ("task-todo", "Todo <b>b</b>"), ("text", "plain")]);
+        let nested = items("- [x] parent\n  - [ ] child `c`\n\n  more\n- [ ] next\n");
+        let depths: Vec<(&str, &str, Option<u8>)> = nested.iter()
+            .filter(|item| !item.category.starts_with("break"))
+            .map(|item| (item.category.as_str(), item.text.as_str(), item.depth))
+            .collect();
+        assert_eq!(depths, vec![
+            ("task-done", "parent", Some(1)),
+            ("task-todo", "child <code>c</code>", Some(2)),
+            ("text", "more", Some(1)),
+            ("task-todo", "next", Some(1)),
+        ]);
+    }
+
+    // the cached entry for key, if any, by the tag it holds
+    fn cached_tag(cache: &mut ReleaseCache, key: &ReleaseKey) -> Option<(String, bool)> {
+        cache.find(|entry| entry.key == *key).map(|entry| (entry.response.tag.clone(), entry.response.latest))
+    }
+
+    #[test]
+    fn pinned_then_latest_are_separate_entries() {
+        let mut cache = ReleaseCache::new();
+        store_release(&mut cache, ApiResponse::from_release("o", "r", Lookup::Tag, release("v2", None, "")), Duration::from_secs(60));
+        // v2 is also the latest release, but nothing has been cached for latest yet
+        assert_eq!(cached_tag(&mut cache, &ReleaseKey::new("o", "r", Lookup::Latest, "")), None);
+        store_release(&mut cache, ApiResponse::from_release("o", "r", Lookup::Latest, release("v2", None, "")), Duration::from_secs(60));
+        assert_eq!(cached_tag(&mut cache, &ReleaseKey::new("o", "r", Lookup::Latest, "")), Some(("v2".to_string(), true)));
+        assert_eq!(cached_tag(&mut cache, &ReleaseKey::new("o", "r", Lookup::Tag, "v2")), Some(("v2".to_string(), false)));
+    }
+
+    #[test]
+    fn latest_then_pinned_are_separate_entries() {
+        let mut cache = ReleaseCache::new();
+        store_release(&mut cache, ApiResponse::from_release("o", "r", Lookup::Latest, release("v1", None, "")), Duration::from_secs(60));
+        // a pin on the tag latest happens to hold isn't answered with the latest entry
+        assert_eq!(cached_tag(&mut cache, &ReleaseKey::new("o", "r", Lookup::Tag, "v1")), None);
+        store_release(&mut cache, ApiResponse::from_release("o", "r", Lookup::Tag, release("v1", None, "")), Duration::from_secs(60));
+        // once v2 ships, latest moves on and the pin stays on v1
+        store_release(&mut cache, ApiResponse::from_release("o", "r", Lookup::Latest, release("v2", None, "")), Duration::from_secs(60));
+        assert_eq!(cached_tag(&mut cache, &ReleaseKey::new("o", "r", Lookup::Tag, "v1")), Some(("v1".to_string(), false)));
+        assert_eq!(cached_tag(&mut cache, &ReleaseKey::new("o", "r", Lookup::Latest, "")), Some(("v2".to_string(), true)));
+    }
+
+    #[test]
+    fn resolved_by_round_trips_and_rejects_unknown_lookups() {
+        for (lookup, name) in [(Lookup::Latest, "latest"), (Lookup::LatestAny, "latest-any"), (Lookup::Prerelease, "prerelease"),
+            (Lookup::Tag, "tag"), (Lookup::List, "list")] {
+            assert_eq!(serde_json::to_value(lookup).unwrap(), name);
+            assert_eq!(serde_json::from_value::<Lookup>(name.into()).unwrap(), lookup);
+        }
+        assert!(serde_json::from_value::<Lookup>("newest".into()).is_err());
+    }
+
+    #[test]
+    fn sub_bullets_sit_one_deeper() {
+        let items = items("- parent\n  - child one\n  - child two\n- next\n");
+        let depths: Vec<(&str, Option<u8>)> = items.iter().map(|item| (item.text.as_str(), item.depth)).collect();
+        assert_eq!(depths, vec![("parent", Some(1)), ("child one", Some(2)), ("child two", Some(2)), ("next", Some(1))]);
+    }
+
+    #[test]
+    fn strikethrough_and_callouts() {
+        assert_eq!(shape(&items("~~old~~")), vec![("text", "<s>old</s>")]);
+        assert_eq!(shape(&items("> [!WARNING] be careful")), vec![("callout-warning", "be careful")]);
+        assert_eq!(shape(&items("> just a quote")), vec![("quote", "just a quote")]);
+    }
+
+    #[test]
+    fn admin_needs_the_exact_token() {
+        let mut config = Config::from_env();
+        config.admin_token = Some("secret".to_string());
+        let bearer = |value: &str| [(AUTHORIZATION, HeaderValue::from_str(value).unwrap())].into_iter().collect::<HeaderMap>();
+        assert!(is_admin(&config, &bearer("Bearer secret")));
+        for wrong in ["Bearer secreT", "Bearer secret2", "Bearer ", "secret"] {
+            assert!(!is_admin(&config, &bearer(wrong)));
+        }
+        assert!(!is_admin(&config, &HeaderMap::new()));
+    }
+
+    #[test]
+    fn non_breaking_isnt_the_breaking_section() {
+        let config = Config::from_env();
+        let notes = items("## Non-breaking changes\n\n- a\n\n## Breaking\n\n- b\n\n## Fixes\n\n- c\n");
+        assert_eq!(shape(&Item::section(&notes, &config.breaking_patterns)), vec![("text", "b")]);
+        let emoji = items("## ⚠️ Breaking Changes\n\n- d\n");
+        assert_eq!(shape(&Item::section(&emoji, &config.breaking_patterns)), vec![("text", "d")]);
+    }
+
+    #[test]
+    fn requirements_need_a_version() {
+        let config = Config::from_env();
+        let notes = items("- Requires Node >= 18\n- this release requires no migration\n- Minimum supported Rust version is now 1.70\n- see the requirements page\n");
+        assert_eq!(Item::requirements(&notes, &config.requirement_patterns), vec![
+            "Requires Node >= 18".to_string(),
+            "Minimum supported Rust version is now 1.70".to_string(),
+        ]);
+    }
 }
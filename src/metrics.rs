@@ -0,0 +1,78 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use axum::extract::{Request, State};
+use axum::http::header::CONTENT_TYPE;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+
+use crate::CacheState;
+
+// counters for /metrics, shared by every handler through the app state
+pub struct Metrics {
+    cache_hits: AtomicU64,
+    cache_misses: AtomicU64,
+    github_calls: AtomicU64,
+    // responses by status code, sorted so the output is stable
+    responses: Mutex<BTreeMap<u16, u64>>,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Metrics {
+            cache_hits: AtomicU64::new(0),
+            cache_misses: AtomicU64::new(0),
+            github_calls: AtomicU64::new(0),
+            responses: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    pub fn cache_hit(&self) {
+        self.cache_hits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn cache_miss(&self) {
+        self.cache_misses.fetch_add(1, Ordering::Relaxed);
+    }
+
+    // one request to the github api, whatever it returned
+    pub fn github_call(&self) {
+        self.github_calls.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn response(&self, status: u16) {
+        let mut responses = self.responses.lock().unwrap_or_else(|e| e.into_inner());
+        *responses.entry(status).or_insert(0) += 1;
+    }
+}
+
+// count every response by its status, including errors and /metrics itself
+pub async fn count_status(State(state): State<CacheState>, request: Request, next: Next) -> Response {
+    let response = next.run(request).await;
+    state.metrics.response(response.status().as_u16());
+    response
+}
+
+// prometheus text exposition format
+pub async fn get_metrics(State(state): State<CacheState>) -> Response {
+    let metrics = &state.metrics;
+    let cached_releases = state.releases.lock().await.len();
+    let mut out = String::new();
+    let counters = [
+        ("released_cache_hits_total", "Release lookups served from the cache.", &metrics.cache_hits),
+        ("released_cache_misses_total", "Release lookups that had to go to github.", &metrics.cache_misses),
+        ("released_github_calls_total", "Requests made to the github api.", &metrics.github_calls),
+    ];
+    // writing to a String can't fail
+    for (name, help, value) in counters {
+        let _ = writeln!(out, "# HELP {} {}\n# TYPE {} counter\n{} {}", name, help, name, name, value.load(Ordering::Relaxed));
+    }
+    let _ = writeln!(out, "# HELP released_responses_total Responses sent, by status code.\n# TYPE released_responses_total counter");
+    for (status, count) in metrics.responses.lock().unwrap_or_else(|e| e.into_inner()).iter() {
+        let _ = writeln!(out, "released_responses_total{{status=\"{}\"}} {}", status, count);
+    }
+    let _ = writeln!(out, "# HELP released_cache_entries Releases currently cached.\n# TYPE released_cache_entries gauge\nreleased_cache_entries {}", cached_releases);
+    ([(CONTENT_TYPE, "text/plain; version=0.0.4")], out).into_response()
+}
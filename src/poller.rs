@@ -0,0 +1,68 @@
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use tokio::sync::Semaphore;
+use tracing::Instrument;
+
+use crate::config::PollTarget;
+use crate::{cached_etag, fetch_release, revalidate, store_release, CacheState, Fetched, Lookup, ReleaseKey};
+
+// background refresh of the latest release for repos we can't install a webhook on,
+// each repo gets its own schedule but they share a limit on concurrent github calls
+pub fn spawn(state: CacheState) {
+    if state.config.poll_repos.is_empty() {
+        return;
+    }
+    let permits = Arc::new(Semaphore::new(state.config.poll_concurrency));
+    for target in state.config.poll_repos.clone() {
+        tracing::info!(org = %target.org, repo = %target.repo, interval_secs = target.interval.as_secs(), "polling repo");
+        let span = tracing::info_span!("poll", org = %target.org, repo = %target.repo);
+        tokio::spawn(poll(state.clone(), target, permits.clone()).instrument(span));
+    }
+}
+
+async fn poll(state: CacheState, target: PollTarget, permits: Arc<Semaphore>) {
+    loop {
+        let delay = {
+            // the semaphore is never closed so acquiring can't fail
+            let _permit = permits.acquire().await.expect("poll semaphore closed");
+            refresh(&state, &target).await
+        };
+        tokio::time::sleep(delay).await;
+    }
+}
+
+// refresh one repo, returning how long to wait before the next attempt
+async fn refresh(state: &CacheState, target: &PollTarget) -> Duration {
+    // checking the rate limit doesn't count against it, so look before we spend a request
+    state.metrics.github_call();
+    match octocrab::instance().ratelimit().get().await {
+        Ok(limit) if limit.resources.core.remaining < state.config.poll_min_rate_remaining => {
+            let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+            let until_reset = Duration::from_secs(limit.resources.core.reset.saturating_sub(now));
+            tracing::warn!(remaining = limit.resources.core.remaining, "skipping poll, github rate limit is low");
+            return target.interval.max(until_reset);
+        },
+        Err(e) => tracing::warn!("failed to check rate limit before polling: {}", e),
+        _ => {}
+    }
+
+    let key = ReleaseKey::new(&target.org, &target.repo, Lookup::Latest, "");
+    let etag = cached_etag(&mut *state.releases.lock().await, &key);
+    state.metrics.github_call();
+    match fetch_release(&target.org, &target.repo, None, etag).await {
+        Ok(Fetched::Modified(response)) => {
+            tracing::info!(tag = %response.tag, "refreshed latest release");
+            let ttl = state.release_ttl(response.resolved_by);
+            let mut cache = state.releases.lock().await;
+            store_release(&mut cache, *response, ttl);
+        },
+        // if the entry was evicted meanwhile the next poll fetches it in full
+        Ok(Fetched::NotModified) => {
+            tracing::info!("latest release unchanged");
+            revalidate(&mut *state.releases.lock().await, &key, state.release_ttl(Lookup::Latest));
+        },
+        Err(e) => tracing::error!("failed to refresh latest release: {}", e.message)
+    }
+    target.interval
+}
@@ -0,0 +1,61 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// rate is measured over fixed windows of this length
+const WINDOW: Duration = Duration::from_secs(60);
+
+// rolling count of release lookups, used to scale the cache ttl with load
+pub struct RequestRate {
+    window: Mutex<Window>,
+}
+
+struct Window {
+    started: Instant,
+    count: u64,
+    // requests per second over the last full window
+    last_rate: Option<f64>,
+}
+
+impl RequestRate {
+    pub fn new() -> Self {
+        RequestRate {
+            window: Mutex::new(Window {
+                started: Instant::now(),
+                count: 0,
+                last_rate: None,
+            }),
+        }
+    }
+
+    pub fn record(&self) {
+        let mut window = self.window.lock().unwrap_or_else(|e| e.into_inner());
+        window.roll();
+        window.count += 1;
+    }
+
+    // requests per second over the last full window, or the current one until a window has finished
+    pub fn per_sec(&self) -> f64 {
+        let mut window = self.window.lock().unwrap_or_else(|e| e.into_inner());
+        window.roll();
+        match window.last_rate {
+            Some(rate) => rate,
+            None => window.count as f64 / window.started.elapsed().as_secs_f64().max(1.0)
+        }
+    }
+}
+
+impl Window {
+    fn roll(&mut self) {
+        let elapsed = self.started.elapsed();
+        if elapsed < WINDOW {
+            return;
+        }
+        // a gap of more than one window means nothing came in during the last one
+        self.last_rate = Some(match elapsed < WINDOW * 2 {
+            true => self.count as f64 / elapsed.as_secs_f64(),
+            false => 0.0
+        });
+        self.started = Instant::now();
+        self.count = 0;
+    }
+}
@@ -0,0 +1,66 @@
+use std::fs;
+
+use serde::Deserialize;
+
+use crate::{sha256_hex, store_release, ApiResponse, AppState, Item};
+
+// one seeded release: a response as served by /:org/:repo, plus the fields responses leave out.
+// without `body`, ?format=markdown|html, ?summary and ?title_from_body have nothing to work from
+#[derive(Deserialize)]
+struct SeedEntry {
+    #[serde(flatten)]
+    response: ApiResponse,
+    // the release's raw markdown notes
+    #[serde(default)]
+    body: Option<String>,
+    // whether the release had no name of its own, defaults to the title being just the tag
+    #[serde(default)]
+    untitled: Option<bool>,
+    #[serde(default)]
+    etag: Option<String>,
+}
+
+impl SeedEntry {
+    fn into_response(self) -> ApiResponse {
+        let mut response = self.response;
+        response.untitled = self.untitled.unwrap_or(response.title == response.tag);
+        response.etag = self.etag;
+        if let Some(body) = self.body {
+            response.body_hash = sha256_hex(&body);
+            response.lead = Item::lead_text(&body);
+            response.body = body;
+        }
+        response
+    }
+}
+
+// preload the cache from a known-good snapshot, independent of any live persistence.
+// a missing or malformed file is logged and otherwise ignored
+pub async fn load(state: &AppState) {
+    let Some(path) = &state.config.seed_cache_file else {
+        return;
+    };
+    let seeded: Vec<SeedEntry> = match fs::read_to_string(path).map(|raw| serde_json::from_str(&raw)) {
+        Ok(Ok(seeded)) => seeded,
+        Ok(Err(e)) => {
+            tracing::error!("ignoring malformed seed cache file {}: {}", path, e);
+            return;
+        },
+        Err(e) => {
+            tracing::error!("failed to read seed cache file {}: {}", path, e);
+            return;
+        }
+    };
+
+    let count = seeded.len();
+    let without_body = seeded.iter().filter(|entry| entry.body.is_none()).count();
+    if without_body > 0 {
+        tracing::warn!("{} seeded releases have no body, ?format, ?summary and ?title_from_body will be empty for them", without_body);
+    }
+    let mut cache = state.releases.lock().await;
+    for entry in seeded {
+        // the grace period stands in for the normal ttl so pinned data stays put during a run
+        store_release(&mut cache, entry.into_response(), state.config.seed_grace);
+    }
+    tracing::info!("seeded {} releases from {}", count, path);
+}